@@ -0,0 +1,162 @@
+use std::collections::BTreeMap;
+
+/// Global options that take a value, so their value isn't mistaken for the
+/// command.
+const OPTIONS_WITH_VALUES: [&str; 2] = ["--config", "--book"];
+
+/// Expands a leading alias the way git does: with `standup = "list --from
+/// yesterday"`, `acty standup --to today` runs `acty list --from yesterday
+/// --to today`. Aliases may refer to other aliases, but never replace one of
+/// the `commands`.
+pub fn expand(
+    aliases: &BTreeMap<String, String>,
+    commands: &[&str],
+    mut args: Vec<String>,
+) -> Result<Vec<String>, String> {
+    let mut expanded: Vec<String> = Vec::new();
+    loop {
+        let position = match command_position(&args) {
+            Some(position) => position,
+            None => return Ok(args),
+        };
+        let name = args[position].clone();
+        if commands.contains(&name.as_str()) {
+            return Ok(args);
+        }
+        let alias = match aliases.get(&name) {
+            Some(alias) => alias,
+            None => return Ok(args),
+        };
+        if expanded.contains(&name) {
+            return Err(format!("Alias '{}' refers back to itself.", name));
+        }
+
+        let words = split_words(alias).map_err(|e| format!("Invalid alias '{}': {}.", name, e))?;
+        if words.is_empty() {
+            return Err(format!("Alias '{}' is empty.", name));
+        }
+        args.splice(position..=position, words);
+        expanded.push(name);
+    }
+}
+
+/// The value of `--config` in `args`, if given.
+pub fn config_path(args: &[String]) -> Option<String> {
+    let mut iter = args.iter().skip(1);
+    while let Some(arg) = iter.next() {
+        if arg == "--" {
+            break;
+        } else if arg == "--config" {
+            return iter.next().cloned();
+        } else if let Some(path) = arg.strip_prefix("--config=") {
+            return Some(path.to_string());
+        }
+    }
+    None
+}
+
+/// Where the command is in `args`: the first argument that isn't a global
+/// option or its value.
+fn command_position(args: &[String]) -> Option<usize> {
+    let mut index = 1;
+    while index < args.len() {
+        let arg = &args[index];
+        if arg == "--" {
+            return None;
+        } else if OPTIONS_WITH_VALUES.contains(&arg.as_str()) {
+            index += 2;
+        } else if arg.starts_with('-') {
+            index += 1;
+        } else {
+            return Some(index);
+        }
+    }
+    None
+}
+
+/// Splits an alias into arguments like a shell would, honoring single and
+/// double quotes and backslash escapes.
+fn split_words(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    // None between words, so `""` still makes an (empty) argument
+    let mut current: Option<String> = None;
+    let mut quote: Option<char> = None;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('\''), c) => current.get_or_insert_with(String::new).push(c),
+            (_, '\\') => match chars.next() {
+                Some(escaped) => current.get_or_insert_with(String::new).push(escaped),
+                None => return Err("it ends with a backslash".to_string()),
+            },
+            (Some(_), c) => current.get_or_insert_with(String::new).push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                current.get_or_insert_with(String::new);
+            }
+            (None, c) if c.is_whitespace() => words.extend(current.take()),
+            (None, c) => current.get_or_insert_with(String::new).push(c),
+        }
+    }
+
+    if let Some(q) = quote {
+        return Err(format!("a {} quote is never closed", q));
+    }
+    words.extend(current);
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split(' ').map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_split_words() {
+        assert_eq!(
+            split_words(r#"search "some text" -t 'a b' \"x\" """#).unwrap(),
+            vec!["search", "some text", "-t", "a b", "\"x\"", ""]
+        );
+        assert!(split_words("log \"open").is_err());
+    }
+
+    #[test]
+    fn test_expand() {
+        let aliases: BTreeMap<String, String> = [
+            ("standup", "list --from yesterday -t work"),
+            ("brk", "log -t break"),
+            ("coffee", "brk Coffee"),
+            ("list", "list -a"),
+            ("loop", "loop"),
+        ]
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        let commands = ["list", "log"];
+
+        assert_eq!(
+            expand(&aliases, &commands, args("acty --book work standup --to today")).unwrap(),
+            args("acty --book work list --from yesterday -t work --to today")
+        );
+        assert_eq!(
+            expand(&aliases, &commands, args("acty coffee")).unwrap(),
+            args("acty log -t break Coffee")
+        );
+        // Commands can't be replaced
+        assert_eq!(
+            expand(&aliases, &commands, args("acty list")).unwrap(),
+            args("acty list")
+        );
+        assert!(expand(&aliases, &commands, args("acty loop")).is_err());
+
+        assert_eq!(
+            config_path(&args("acty list --config /tmp/acty.toml")),
+            Some("/tmp/acty.toml".to_string())
+        );
+    }
+}
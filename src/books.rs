@@ -0,0 +1,206 @@
+use crate::config::{Backend, Config};
+use crate::journal::{self, Change};
+use crate::log_entry::LogEntry;
+use crate::logger::select_ids;
+use crate::output::{self, OutputFormat};
+use crate::storage::{self, Store};
+use crate::util::Filter;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+#[derive(Serialize)]
+struct BookRecord {
+    name: String,
+    current: bool,
+    backend: &'static str,
+    location: String,
+    entries: usize,
+}
+
+pub fn list_books(config: &Config, format: OutputFormat) {
+    let mut records = Vec::new();
+    for name in config.book_names() {
+        let book = match config.book(&name) {
+            Some(book) => book,
+            None => continue,
+        };
+        let (backend, location) = match book.backend {
+            Backend::Json => ("json", PathBuf::from(&book.log_file)),
+            Backend::Sqlite => ("sqlite", book.database_path()),
+        };
+        // Don't create a database (or lock file) just to count nothing
+        let entries = if location.exists() {
+            let _lock = storage::lock_shared(&book);
+            storage::open(&book)
+                .read(Store::Active, &Filter::default())
+                .len()
+        } else {
+            0
+        };
+        records.push(BookRecord {
+            current: name == config.book,
+            name,
+            backend,
+            location: location.to_string_lossy().into_owned(),
+            entries,
+        });
+    }
+
+    if format != OutputFormat::Table {
+        output::print_json(format, "books", "book", &records);
+        return;
+    }
+
+    println!("  Book\t\tEntries\tLocation");
+    println!("  ----\t\t-------\t--------");
+    for record in &records {
+        println!(
+            "{} {}\t\t{}\t{}",
+            if record.current { "*" } else { " " },
+            record.name,
+            record.entries,
+            record.location
+        );
+    }
+}
+
+/// Moves entries from the current logbook to the same place (log or archive)
+/// in logbook `target`. Each book's history records its side of the move.
+pub fn move_to_book(config: &Config, target: &str, selectors: Vec<String>) {
+    let target_config = match config.for_book(Some(target)) {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    if target_config.book == config.book {
+        eprintln!("The entries are already in book '{}'.", target);
+        return;
+    }
+
+    // Always lock in the same order, so two moves in opposite directions
+    // can't wait for each other forever
+    let _locks = if storage::companion_path(config, "lock")
+        < storage::companion_path(&target_config, "lock")
+    {
+        [storage::lock(config), storage::lock(&target_config)]
+    } else {
+        [storage::lock(&target_config), storage::lock(config)]
+    };
+    let source = storage::open(config);
+    let destination = storage::open(&target_config);
+    let taken: HashSet<String> = [Store::Active, Store::Archive, Store::Trash]
+        .iter()
+        .flat_map(|&store| destination.read(store, &Filter::default()))
+        .map(|e| e.id)
+        .collect();
+
+    let mut source_changes = Vec::new();
+    let mut destination_changes = Vec::new();
+    let mut count = 0;
+    for store in [Store::Active, Store::Archive] {
+        let ids = select_ids(source.as_ref(), store, &selectors);
+        if ids.is_empty() {
+            continue;
+        }
+        let entries: Vec<LogEntry> = source
+            .read(store, &Filter::default())
+            .into_iter()
+            .filter(|e| ids.contains(&e.id))
+            .collect();
+        let moved: Vec<LogEntry> = entries
+            .iter()
+            .cloned()
+            .map(|mut entry| {
+                if taken.contains(&entry.id) {
+                    entry.reassign_id();
+                }
+                entry
+            })
+            .collect();
+
+        // Copy first, so a crash leaves the entries in both books rather
+        // than in neither
+        destination.insert(store, &moved);
+        source.delete(store, &ids);
+        count += moved.len();
+        source_changes.push(Change::Remove { store, entries });
+        destination_changes.push(Change::Add {
+            store,
+            entries: moved,
+        });
+    }
+
+    journal::record(
+        config,
+        &format!("move {} --to {}", selectors.join(" "), target),
+        source_changes,
+    );
+    journal::record(
+        &target_config,
+        &format!("move from {}", config.book),
+        destination_changes,
+    );
+
+    println!("Moved {} log entry(ies) to book '{}'.", count, target);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Book;
+    use crate::logger::log_action;
+    use std::fs;
+
+    #[test]
+    fn test_move_to_book() {
+        let test_dir = std::env::temp_dir().join("acty_test_books");
+        let _ = fs::remove_dir_all(&test_dir);
+        let mut config = Config {
+            log_file: test_dir.join("action_log.json").to_string_lossy().to_string(),
+            ..Config::default()
+        };
+        config.books.insert("work".to_string(), Book::default());
+        let main = config.for_book(None).unwrap();
+        let work = config.for_book(Some("work")).unwrap();
+        assert_eq!(
+            PathBuf::from(&work.log_file),
+            test_dir.join("books").join("work").join("action_log.json")
+        );
+
+        log_action(&main, "Personal".to_string(), vec![], None, None, None);
+        log_action(&main, "Client call".to_string(), vec![], None, None, None);
+        move_to_book(&main, "work", vec!["2".to_string()]);
+
+        let contents = |config: &Config| -> Vec<String> {
+            storage::open(config)
+                .read(Store::Active, &Filter::default())
+                .into_iter()
+                .map(|e| e.content)
+                .collect()
+        };
+        assert_eq!(contents(&main), vec!["Personal"]);
+        assert_eq!(contents(&work), vec!["Client call"]);
+
+        // Undo in the destination book only removes the copy there
+        journal::undo(&work);
+        assert!(contents(&work).is_empty());
+        journal::undo(&main);
+        assert_eq!(contents(&main), vec!["Personal", "Client call"]);
+
+        // Two books can't share a directory
+        config.books.insert(
+            "clash".to_string(),
+            Book {
+                log_file: Some(test_dir.join("other.json").to_string_lossy().to_string()),
+                ..Book::default()
+            },
+        );
+        assert!(config.for_book(Some("clash")).is_err());
+        assert!(config.for_book(Some("nope")).is_err());
+
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+}
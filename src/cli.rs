@@ -1,23 +1,451 @@
-use crate::config::Config;
-use clap::{App, Arg, SubCommand};
+use crate::config::{self, Config};
+use crate::dates::{parse_period, parse_timestamp, DateRange};
+use crate::output::OutputFormat;
+use crate::report::ReportOptions;
+use crate::storage::Scope;
+use crate::query::Query;
+use crate::util::{parse_duration, Filter};
+use chrono::{DateTime, Local};
+use clap::{App, Arg, ArgMatches, ErrorKind, SubCommand};
+use std::collections::HashMap;
+use std::io::Read;
 
+use crate::alias;
+use crate::books;
+use crate::doctor;
+use crate::editor;
+use crate::export;
+use crate::import;
+use crate::journal;
 use crate::list;
 use crate::logger;
 use crate::markdown;
+use crate::migrate;
+use crate::report;
 use crate::tags;
+use crate::trash;
+use crate::tui;
 
-pub fn run(config: &Config) {
-    let matches = App::new("Action Logger")
+pub fn run() {
+    let args: Vec<String> = std::env::args_os()
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect();
+    // Read before parsing the arguments, since they may use aliases from it.
+    // A broken config is only reported after parsing, so --help and
+    // --version still work.
+    let config_path = alias::config_path(&args).or_else(|| std::env::var("ACTY_CONFIG").ok());
+    let loaded = Config::load(config_path.as_deref());
+    let app = build_cli();
+    let names = commands(&app);
+    let names: Vec<&str> = names.iter().map(String::as_str).collect();
+    let aliases = loaded.as_ref().map(|c| c.aliases.clone()).unwrap_or_default();
+    let args = match alias::expand(&aliases, &names, args) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+
+    let matches = match app.get_matches_from_safe(args) {
+        Ok(matches) => matches,
+        Err(e) if e.kind == ErrorKind::HelpDisplayed || e.kind == ErrorKind::VersionDisplayed => e.exit(),
+        // Whatever is wrong with the command line may well be down to the
+        // aliases that couldn't be read
+        Err(e) => match loaded {
+            Ok(_) => e.exit(),
+            Err(config_error) => {
+                eprintln!("{}", config_error);
+                return;
+            }
+        },
+    };
+    let config = match loaded {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+
+    let book = match matches.subcommand() {
+        (_, Some(sub_matches)) => sub_matches.value_of("book"),
+        _ => matches.value_of("book"),
+    };
+    let config = &match config.for_book(book) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+
+    match matches.subcommand() {
+        ("log", Some(sub_matches)) => {
+            let notes = match parse_note(sub_matches, &mut std::io::stdin()) {
+                Ok(notes) => notes,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    return;
+                }
+            };
+            let content = sub_matches.value_of("content");
+            let (content, notes) = if sub_matches.is_present("editor") {
+                match editor::compose(content, notes.as_deref()) {
+                    Ok(Some(written)) => written,
+                    Ok(None) => {
+                        eprintln!("Nothing was logged: the entry is empty.");
+                        return;
+                    }
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        return;
+                    }
+                }
+            } else {
+                (content.unwrap().to_string(), notes)
+            };
+            let tags: Vec<String> = sub_matches
+                .value_of("tags")
+                .unwrap_or("")
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect();
+            let duration = match sub_matches.value_of("for").map(parse_duration) {
+                Some(Ok(seconds)) => Some(seconds),
+                Some(Err(e)) => {
+                    eprintln!("{}", e);
+                    return;
+                }
+                None => None,
+            };
+            let at = match parse_at(sub_matches) {
+                Ok(at) => at,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    return;
+                }
+            };
+            logger::log_action(config, content, tags, duration, at, notes);
+        }
+        ("start", Some(sub_matches)) => {
+            let content = sub_matches.value_of("content").unwrap().to_string();
+            let tags: Vec<String> = sub_matches
+                .value_of("tags")
+                .unwrap_or("")
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect();
+            logger::start_timer(config, content, tags);
+        }
+        ("stop", Some(_)) => {
+            logger::stop_timer(config);
+        }
+        ("status", Some(sub_matches)) => {
+            logger::timer_status(config, parse_output(config, sub_matches));
+        }
+        ("list", Some(sub_matches)) => {
+            let scope = parse_scope(sub_matches);
+            match parse_filter(config, sub_matches) {
+                Ok(filter) => list::list_logs(config, filter, scope, parse_output(config, sub_matches)),
+                Err(e) => eprintln!("{}", e),
+            }
+        }
+        ("search", Some(sub_matches)) => {
+            let scope = parse_scope(sub_matches);
+            match parse_filter(config, sub_matches) {
+                Ok(mut filter) => {
+                    filter.search = sub_matches.value_of("keyword").map(|s| s.to_string());
+                    list::list_logs(config, filter, scope, parse_output(config, sub_matches));
+                }
+                Err(e) => eprintln!("{}", e),
+            }
+        }
+        ("show", Some(sub_matches)) => {
+            list::show_log(
+                config,
+                sub_matches.value_of("id").unwrap(),
+                parse_output(config, sub_matches),
+            );
+        }
+        ("tui", Some(sub_matches)) => {
+            let scope = parse_scope(sub_matches);
+            match parse_filter(config, sub_matches) {
+                Ok(filter) => tui::browse(config, filter, scope),
+                Err(e) => eprintln!("{}", e),
+            }
+        }
+        ("delete", Some(sub_matches)) => {
+            let ids: Vec<String> = sub_matches
+                .values_of("id")
+                .unwrap()
+                .map(|id| id.to_string())
+                .collect();
+            logger::delete_logs(config, ids);
+        }
+        ("edit", Some(sub_matches)) if sub_matches.is_present("editor") => {
+            match parse_filter(config, sub_matches) {
+                Ok(filter) => editor::edit_in_editor(
+                    config,
+                    sub_matches.value_of("id"),
+                    filter,
+                    parse_scope(sub_matches),
+                ),
+                Err(e) => eprintln!("{}", e),
+            }
+        }
+        ("edit", Some(sub_matches)) => {
+            let id = sub_matches.value_of("id").unwrap();
+            let content = sub_matches.value_of("content").map(|s| s.to_string());
+            let tags = sub_matches.value_of("tags").map(|t| {
+                t.split(',')
+                    .map(|s| s.trim().to_string())
+                    .filter(|s| !s.is_empty())
+                    .collect()
+            });
+            let at = match parse_at(sub_matches) {
+                Ok(at) => at,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    return;
+                }
+            };
+            let notes = match parse_note(sub_matches, &mut std::io::stdin()) {
+                Ok(notes) => notes,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    return;
+                }
+            };
+            logger::update_log(config, id, content, tags, at, notes);
+        }
+        ("copy", Some(sub_matches)) => {
+            let id = sub_matches.value_of("id").unwrap();
+            let content = sub_matches.value_of("content").map(|s| s.to_string());
+            let at = match parse_at(sub_matches) {
+                Ok(at) => at,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    return;
+                }
+            };
+            logger::copy_log(config, id, content, at);
+        }
+        ("tags", Some(sub_matches)) if sub_matches.subcommand_name().is_some() => {
+            let (name, change_matches) = sub_matches.subcommand();
+            // subcommand_name() guarantees the matches are there
+            let change_matches = change_matches.unwrap();
+            let scope = if change_matches.is_present("archive") {
+                Scope::All
+            } else {
+                Scope::Active
+            };
+            let dry_run = change_matches.is_present("dry-run");
+            match name {
+                "rename" => tags::rename_tag(
+                    config,
+                    change_matches.value_of("from").unwrap(),
+                    change_matches.value_of("to").unwrap(),
+                    scope,
+                    dry_run,
+                ),
+                "merge" => {
+                    let sources: Vec<String> = change_matches
+                        .values_of("tags")
+                        .unwrap()
+                        .map(|t| t.to_string())
+                        .collect();
+                    let into = change_matches.value_of("into").unwrap();
+                    tags::merge_tags(config, &sources, into, scope, dry_run)
+                }
+                _ => tags::delete_tag(config, change_matches.value_of("tag").unwrap(), scope, dry_run),
+            }
+        }
+        ("tags", Some(sub_matches)) => {
+            let scope = parse_scope(sub_matches);
+            let depth = match parse_depth(sub_matches) {
+                Ok(depth) => depth,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    return;
+                }
+            };
+            match parse_filter(config, sub_matches) {
+                Ok(filter) => {
+                    tags::list_tags(config, filter, scope, depth, parse_output(config, sub_matches))
+                }
+                Err(e) => eprintln!("{}", e),
+            }
+        }
+        ("trash", Some(sub_matches)) => match sub_matches.subcommand() {
+            ("restore", Some(restore_matches)) => {
+                let ids: Vec<String> = restore_matches
+                    .values_of("id")
+                    .unwrap()
+                    .map(|id| id.to_string())
+                    .collect();
+                trash::restore(config, ids);
+            }
+            ("empty", Some(empty_matches)) => {
+                match empty_matches.value_of("older-than").map(parse_duration) {
+                    Some(Ok(seconds)) => trash::empty_trash(config, Some(seconds)),
+                    Some(Err(e)) => eprintln!("{}", e),
+                    None => trash::empty_trash(config, None),
+                }
+            }
+            ("list", Some(list_matches)) => trash::list_trash(config, parse_output(config, list_matches)),
+            _ => trash::list_trash(config, config.output),
+        },
+        ("unarchive", Some(sub_matches)) => match parse_filter(config, sub_matches) {
+            Ok(filter) => logger::unarchive_logs(config, filter, sub_matches.is_present("dry-run")),
+            Err(e) => eprintln!("{}", e),
+        },
+        ("undo", Some(_)) => {
+            journal::undo(config);
+        }
+        ("redo", Some(_)) => {
+            journal::redo(config);
+        }
+        ("history", Some(sub_matches)) => {
+            let limit = sub_matches.value_of("limit").unwrap();
+            match limit.parse::<usize>() {
+                Ok(limit) => journal::history(config, limit),
+                Err(_) => eprintln!("Invalid limit: {}", limit),
+            }
+        }
+        ("archive", Some(sub_matches)) => {
+            let days = match sub_matches.value_of("days") {
+                Some(days) => match days.parse::<i64>() {
+                    Ok(days) => days,
+                    Err(_) => {
+                        eprintln!("Invalid days: {}. Please provide a number.", days);
+                        return;
+                    }
+                },
+                None => config.archive_days,
+            };
+            logger::archive_logs(config, days);
+        }
+        ("config", Some(_)) => {
+            config::show_config(config);
+        }
+        ("books", Some(sub_matches)) => {
+            books::list_books(config, parse_output(config, sub_matches));
+        }
+        ("move", Some(sub_matches)) => {
+            let ids = sub_matches
+                .values_of("ids")
+                .unwrap()
+                .map(|s| s.to_string())
+                .collect();
+            books::move_to_book(config, sub_matches.value_of("to").unwrap(), ids);
+        }
+        ("doctor", Some(sub_matches)) => {
+            doctor::doctor(
+                config,
+                sub_matches.is_present("fix"),
+                sub_matches.is_present("quarantine"),
+            );
+        }
+        ("migrate", Some(_)) => {
+            migrate::migrate(config);
+        }
+        ("migrate-archive", Some(_)) => {
+            logger::migrate_archive(config);
+        }
+        ("report", Some(sub_matches)) => {
+            let scope = parse_scope(sub_matches);
+            let depth = match parse_depth(sub_matches) {
+                Ok(depth) => depth,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    return;
+                }
+            };
+            // possible_values guarantees these parse
+            let options = ReportOptions {
+                split: sub_matches.value_of("split").unwrap().parse().unwrap(),
+                by: sub_matches.value_of("by").unwrap().parse().unwrap(),
+                combinations: sub_matches.is_present("combinations"),
+                depth,
+            };
+            match parse_filter(config, sub_matches) {
+                Ok(filter) => {
+                    report::report(config, filter, scope, options, parse_output(config, sub_matches))
+                }
+                Err(e) => eprintln!("{}", e),
+            }
+        }
+        ("mdt", Some(sub_matches)) => {
+            let scope = parse_scope(sub_matches);
+            match parse_filter(config, sub_matches) {
+                Ok(filter) => markdown::output_markdown_table(config, filter, scope),
+                Err(e) => eprintln!("{}", e),
+            }
+        }
+        ("export", Some(sub_matches)) => {
+            let scope = parse_scope(sub_matches);
+            let format = sub_matches.value_of("format").unwrap();
+            match parse_filter(config, sub_matches) {
+                Ok(filter) => export::export_logs(config, filter, scope, format),
+                Err(e) => eprintln!("{}", e),
+            }
+        }
+        ("import", Some(sub_matches)) => {
+            let file = sub_matches.value_of("file").unwrap();
+            let mut mapping = HashMap::new();
+            for pair in sub_matches.values_of("map").into_iter().flatten() {
+                match pair.split_once('=') {
+                    Some((field, column)) => {
+                        mapping.insert(field.trim().to_lowercase(), column.trim().to_string());
+                    }
+                    None => {
+                        eprintln!("Invalid column mapping '{}'. Use FIELD=COLUMN.", pair);
+                        return;
+                    }
+                }
+            }
+            import::import_logs(config, file, mapping, sub_matches.is_present("dry-run"));
+        }
+        _ => {
+            println!("No subcommand was used");
+        }
+    }
+}
+
+/// Every command and option acty accepts, once aliases are expanded.
+fn build_cli<'a, 'b>() -> App<'a, 'b> {
+    App::new("Action Logger")
         .version("0.1.0")
         .author("Your Name")
         .about("A simple action logging tool")
+        .arg(
+            // Read by alias::config_path before the arguments are parsed
+            Arg::with_name("config")
+                .long("config")
+                .value_name("FILE")
+                .help("The config file to use (default: $ACTY_CONFIG, or ~/.config/acty/config.toml)")
+                .global(true)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("book")
+                .long("book")
+                .value_name("NAME")
+                .help("The logbook to use (see `acty books`)")
+                .env("ACTY_BOOK")
+                .global(true)
+                .takes_value(true),
+        )
         .subcommand(
             SubCommand::with_name("log")
                 .about("Log a new action")
                 .arg(
                     Arg::with_name("content")
                         .help("The content of the log entry")
-                        .required(true)
+                        .required_unless("editor")
                         .index(1),
                 )
                 .arg(
@@ -27,56 +455,95 @@ pub fn run(config: &Config) {
                         .value_name("TAGS")
                         .help("Comma-separated list of tags")
                         .takes_value(true),
-                ),
-        )
-        .subcommand(
-            SubCommand::with_name("list")
-                .about("List log entries")
+                )
                 .arg(
-                    Arg::with_name("date")
-                        .short("d")
-                        .long("date")
-                        .value_name("DATE")
-                        .help("Filter logs by date (YYYY-MM-DD)")
+                    Arg::with_name("for")
+                        .short("f")
+                        .long("for")
+                        .value_name("DURATION")
+                        .help("How long the action takes (e.g. 25m, 1h30m)")
                         .takes_value(true),
                 )
                 .arg(
-                    Arg::with_name("range")
-                        .short("r")
-                        .long("range")
-                        .value_name("DAYS")
-                        .help("Filter logs by date range (in days)")
-                        .takes_value(true),
+                    Arg::with_name("at")
+                        .long("at")
+                        .value_name("TIME")
+                        .help("When the action happened (e.g. 17:30, 'yesterday 17:30', -15m, RFC 3339)")
+                        .takes_value(true)
+                        .allow_hyphen_values(true),
+                )
+                .arg(note_arg())
+                .arg(
+                    Arg::with_name("editor")
+                        .short("e")
+                        .long("editor")
+                        .help("Write the content (first line) and notes (the lines after it) in $EDITOR"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("start")
+                .about("Start a timer for a new action")
+                .arg(
+                    Arg::with_name("content")
+                        .help("The content of the log entry")
+                        .required(true)
+                        .index(1),
                 )
                 .arg(
                     Arg::with_name("tags")
                         .short("t")
                         .long("tags")
                         .value_name("TAGS")
-                        .help("Filter logs by tags (comma-separated)")
+                        .help("Comma-separated list of tags")
                         .takes_value(true),
-                )
+                ),
+        )
+        .subcommand(SubCommand::with_name("stop").about("Stop the running timer"))
+        .subcommand(
+            SubCommand::with_name("status")
+                .about("Show the running timer")
+                .arg(output_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("list")
+                .about("List log entries")
+                .args(&filter_args())
+                .arg(output_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("search")
+                .about("Search log entries by keyword in content and tags")
                 .arg(
-                    Arg::with_name("search")
-                        .short("s")
-                        .long("search")
-                        .value_name("KEYWORD")
-                        .help("Filter logs by keyword")
-                        .takes_value(true),
+                    Arg::with_name("keyword")
+                        .help("The keyword to search for")
+                        .required(true)
+                        .index(1),
                 )
+                .args(&filter_args())
+                .arg(output_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("show")
+                .about("Show a log entry in full, including its notes")
                 .arg(
-                    Arg::with_name("archive")
-                        .short("a")
-                        .long("archive")
-                        .help("Read from archive file instead of main log"),
-                ),
+                    Arg::with_name("id")
+                        .help("The ID of the log entry to show (ID, unique ID prefix, line number or 'last'; prefix with a: for archived entries)")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(output_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("tui")
+                .about("Browse, filter and edit log entries full-screen")
+                .args(&filter_args()),
         )
         .subcommand(
             SubCommand::with_name("delete")
-                .about("Delete log entries by ID")
+                .about("Move log entries to the trash by ID")
                 .arg(
                     Arg::with_name("id")
-                        .help("The IDs of the log entries to delete (space separated)")
+                        .help("The IDs of the log entries to delete (space separated; ID, unique ID prefix, line number or 'last'; prefix with a: for archived entries)")
                         .required(true)
                         .multiple(true)
                         .index(1),
@@ -84,17 +551,18 @@ pub fn run(config: &Config) {
         )
         .subcommand(
             SubCommand::with_name("edit")
-                .about("Edit a log entry by ID")
+                .about("Edit a log entry by ID, or many at once in $EDITOR")
                 .arg(
                     Arg::with_name("id")
-                        .help("The ID of the log entry to edit")
-                        .required(true)
+                        .help("The ID of the log entry to edit (ID, unique ID prefix, line number or 'last'; prefix with a: for archived entries)")
+                        .required_unless("editor")
                         .index(1),
                 )
                 .arg(
                     Arg::with_name("content")
                         .help("The new content of the log entry")
-                        .required(true)
+                        .required_unless_one(&["tags", "at", "note", "editor"])
+                        .conflicts_with("editor")
                         .index(2),
                 )
                 .arg(
@@ -102,8 +570,30 @@ pub fn run(config: &Config) {
                         .short("t")
                         .long("tags")
                         .value_name("TAGS")
-                        .help("Comma-separated list of new tags (overwrites existing tags)")
+                        .help("Comma-separated list of new tags (overwrites existing tags); with --editor and no ID, only edit entries with these tags")
                         .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("at")
+                        .long("at")
+                        .value_name("TIME")
+                        .help("New time of the log entry (e.g. 17:30, 'yesterday 17:30', -15m, RFC 3339)")
+                        .takes_value(true)
+                        .allow_hyphen_values(true)
+                        .conflicts_with("editor"),
+                )
+                .arg(note_arg().conflicts_with("editor"))
+                .arg(
+                    Arg::with_name("editor")
+                        .long("editor")
+                        .help("Edit the time, tags and content in $EDITOR: the entry with the ID, or every entry matching the filters"),
+                )
+                .args(
+                    &untagged_filter_args()
+                        .into_iter()
+                        .chain(scope_args())
+                        .map(|arg| arg.requires("editor"))
+                        .collect::<Vec<_>>(),
                 ),
         )
         .subcommand(
@@ -111,7 +601,7 @@ pub fn run(config: &Config) {
                 .about("Copy a log entry to a new entry with current timestamp")
                 .arg(
                     Arg::with_name("id")
-                        .help("The ID of the log entry to copy")
+                        .help("The ID of the log entry to copy (ID, unique ID prefix, line number or 'last'; prefix with a: for archived entries)")
                         .required(true)
                         .index(1),
                 )
@@ -119,58 +609,184 @@ pub fn run(config: &Config) {
                     Arg::with_name("content")
                         .help("Optional new content for the copied entry")
                         .index(2),
+                )
+                .arg(
+                    Arg::with_name("at")
+                        .long("at")
+                        .value_name("TIME")
+                        .help("Time of the new entry instead of now (e.g. 17:30, 'yesterday 17:30', -15m, RFC 3339)")
+                        .takes_value(true)
+                        .allow_hyphen_values(true),
                 ),
         )
         .subcommand(
             SubCommand::with_name("tags")
-                .about("List all used tags and their usage counts")
-                .arg(
-                    Arg::with_name("archive")
-                        .short("a")
-                        .long("archive")
-                        .help("Read from archive file instead of main log"),
+                .about("List the used tags as a tree, with usage counts and time rolled up, or rename, merge and delete tags")
+                .args(&filter_args())
+                .arg(depth_arg())
+                .arg(output_arg())
+                .subcommand(
+                    SubCommand::with_name("rename")
+                        .about("Rename a tag, and the tags below it, on every entry")
+                        .arg(Arg::with_name("from").help("The tag to rename").required(true).index(1))
+                        .arg(Arg::with_name("to").help("Its new name").required(true).index(2))
+                        .args(&tag_change_args()),
+                )
+                .subcommand(
+                    SubCommand::with_name("merge")
+                        .about("Replace several tags, and the tags below them, with one")
+                        .arg(
+                            Arg::with_name("tags")
+                                .help("The tags to merge")
+                                .required(true)
+                                .multiple(true)
+                                .index(1),
+                        )
+                        .arg(
+                            Arg::with_name("into")
+                                .long("into")
+                                .value_name("TAG")
+                                .help("The tag they become")
+                                .takes_value(true)
+                                .required(true),
+                        )
+                        .args(&tag_change_args()),
+                )
+                .subcommand(
+                    SubCommand::with_name("delete")
+                        .about("Remove a tag, and the tags below it, from every entry")
+                        .arg(Arg::with_name("tag").help("The tag to remove").required(true).index(1))
+                        .args(&tag_change_args()),
                 ),
         )
+        .subcommand(
+            SubCommand::with_name("report")
+                .about("Sum the time spent per tag, e.g. for timesheets")
+                .args(&filter_args())
+                .arg(
+                    Arg::with_name("by")
+                        .short("b")
+                        .long("by")
+                        .value_name("PERIOD")
+                        .help("Break the report down per day or per week")
+                        .possible_values(&["total", "day", "week"])
+                        .default_value("total"),
+                )
+                .arg(
+                    Arg::with_name("split")
+                        .long("split")
+                        .value_name("MODE")
+                        .help("How an entry's time is shared by its tags: divided evenly, counted in full for each, or given to the first tag")
+                        .possible_values(&["even", "full", "first"])
+                        .default_value("even"),
+                )
+                .arg(
+                    Arg::with_name("combinations")
+                        .short("c")
+                        .long("combinations")
+                        .help("Also sum the time per combination of tags"),
+                )
+                .arg(depth_arg())
+                .arg(output_arg()),
+        )
         .subcommand(
             SubCommand::with_name("mdt")
                 .about("output log entries in markdown table format")
+                .args(&filter_args()),
+        )
+        .subcommand(
+            SubCommand::with_name("export")
+                .about("Export log entries for spreadsheets and other tools")
+                .args(&filter_args())
                 .arg(
-                    Arg::with_name("date")
-                        .short("d")
-                        .long("date")
-                        .value_name("DATE")
-                        .help("Filter logs by date (YYYY-MM-DD)")
-                        .takes_value(true),
-                )
+                    Arg::with_name("format")
+                        .short("f")
+                        .long("format")
+                        .value_name("FORMAT")
+                        .help("Output format")
+                        .possible_values(&["csv"])
+                        .default_value("csv"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("import")
+                .about("Import log entries from a CSV file")
                 .arg(
-                    Arg::with_name("range")
-                        .short("r")
-                        .long("range")
-                        .value_name("DAYS")
-                        .help("Filter logs by date range (in days)")
-                        .takes_value(true),
+                    Arg::with_name("file")
+                        .help("The CSV file to import")
+                        .required(true)
+                        .index(1),
                 )
                 .arg(
-                    Arg::with_name("tags")
-                        .short("t")
-                        .long("tags")
-                        .value_name("TAGS")
-                        .help("Filter logs by tags (comma-separated)")
-                        .takes_value(true),
+                    Arg::with_name("map")
+                        .short("m")
+                        .long("map")
+                        .value_name("FIELD=COLUMN")
+                        .help("Read a field from a differently named column, e.g. content=Task (repeatable; fields: id, timestamp, duration_seconds, duration, tags, content)")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1),
                 )
                 .arg(
-                    Arg::with_name("search")
-                        .short("s")
-                        .long("search")
-                        .value_name("KEYWORD")
-                        .help("Filter logs by keyword")
-                        .takes_value(true),
+                    Arg::with_name("dry-run")
+                        .long("dry-run")
+                        .help("Validate the file without importing anything"),
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("trash")
+                .about("List, restore or permanently delete deleted entries")
+                .subcommand(
+                    SubCommand::with_name("list")
+                        .about("List entries in the trash")
+                        .arg(output_arg()),
+                )
+                .subcommand(
+                    SubCommand::with_name("restore")
+                        .about("Move entries from the trash back into the log")
+                        .arg(
+                            Arg::with_name("id")
+                                .help("The IDs of the entries to restore (ID, unique ID prefix or position in 'trash list')")
+                                .required(true)
+                                .multiple(true)
+                                .index(1),
+                        ),
                 )
+                .subcommand(
+                    SubCommand::with_name("empty")
+                        .about("Permanently delete entries in the trash")
+                        .arg(
+                            Arg::with_name("older-than")
+                                .long("older-than")
+                                .value_name("DURATION")
+                                .help("Only entries deleted longer ago than this (e.g. 30d, 2w)")
+                                .takes_value(true),
+                        ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("unarchive")
+                .about("Move archived entries matching the filters back into the log")
+                .args(&entry_filter_args())
                 .arg(
-                    Arg::with_name("archive")
-                        .short("a")
-                        .long("archive")
-                        .help("Read from archive file instead of main log"),
+                    Arg::with_name("dry-run")
+                        .long("dry-run")
+                        .help("Show which entries would move without moving them"),
+                ),
+        )
+        .subcommand(SubCommand::with_name("undo").about("Undo the last command that changed the log"))
+        .subcommand(SubCommand::with_name("redo").about("Redo the last undone command"))
+        .subcommand(
+            SubCommand::with_name("history")
+                .about("Show recent commands that changed the log")
+                .arg(
+                    Arg::with_name("limit")
+                        .short("n")
+                        .long("limit")
+                        .value_name("N")
+                        .help("How many commands to show")
+                        .takes_value(true)
+                        .default_value("20"),
                 ),
         )
         .subcommand(
@@ -178,124 +794,333 @@ pub fn run(config: &Config) {
                 .about("Move old logs to archive file")
                 .arg(
                     Arg::with_name("days")
-                        .help("Archive logs older than N days (default: 7)")
+                        .help("Archive logs older than N days (default: archive_days from the config, 7 unless set)")
+                        .index(1),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("config")
+                .about("Inspect the configuration")
+                .subcommand(
+                    SubCommand::with_name("show")
+                        .about("Print the effective settings, after --config, --book and ACTY_* overrides"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("books")
+                .about("List the logbooks, marking the one in use")
+                .arg(output_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("move")
+                .about("Move entries to another logbook (archived ones with the a: prefix stay archived)")
+                .arg(
+                    Arg::with_name("ids")
+                        .help("The IDs of the log entries to move (space separated; ID, unique ID prefix, line number or 'last'; prefix with a: for archived entries)")
+                        .required(true)
+                        .multiple(true)
                         .index(1),
+                )
+                .arg(
+                    Arg::with_name("to")
+                        .long("to")
+                        .value_name("BOOK")
+                        .help("The logbook to move them to")
+                        .required(true)
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("doctor")
+                .about("Check the log and archive for unreadable lines, misordered or duplicate entries and empty tags")
+                .arg(
+                    Arg::with_name("fix")
+                        .long("fix")
+                        .help("Repair the problems found, setting unreadable lines and dropped duplicates aside"),
+                )
+                .arg(
+                    Arg::with_name("quarantine")
+                        .long("quarantine")
+                        .help("Only move unreadable lines to the quarantine file next to the log"),
                 ),
         )
-        .get_matches();
+        .subcommand(
+            SubCommand::with_name("migrate")
+                .about("Upgrade stored entries to the current format, keeping a backup of the files"),
+        )
+        .subcommand(
+            SubCommand::with_name("migrate-archive")
+                .about("Split archive.json into the monthly files used by archive_layout = \"monthly\""),
+        )
+}
 
-    match matches.subcommand() {
-        ("log", Some(sub_matches)) => {
-            let content = sub_matches.value_of("content").unwrap().to_string();
-            let tags: Vec<String> = sub_matches
-                .value_of("tags")
-                .unwrap_or("")
-                .split(',')
-                .map(|s| s.trim().to_string())
-                .filter(|s| !s.is_empty())
-                .collect();
-            logger::log_action(&config, content, tags);
-        }
-        ("list", Some(sub_matches)) => {
-            let date = sub_matches.value_of("date").map(|d| d.to_string());
-            let range = sub_matches
-                .value_of("range")
-                .map(|r| r.parse::<i64>().expect("Invalid range"));
-            let tags: Vec<String> = sub_matches
-                .value_of("tags")
-                .unwrap_or("")
-                .split(',')
-                .map(|s| s.trim().to_string())
-                .filter(|s| !s.is_empty())
-                .collect();
-            let search = sub_matches.value_of("search").map(|s| s.to_string());
-            let use_archive = sub_matches.is_present("archive");
-            list::list_logs(&config, date, range, tags, search, use_archive);
-        }
-        ("delete", Some(sub_matches)) => {
-            let ids_result: Result<Vec<usize>, _> = sub_matches
-                .values_of("id")
-                .unwrap()
-                .map(|id_str| {
-                    if id_str == "last" {
-                        Ok(logger::get_log_count(&config))
-                    } else {
-                        id_str.parse::<usize>()
-                    }
-                })
-                .collect();
+/// Every command, which aliases can't replace.
+fn commands(app: &App) -> Vec<String> {
+    app.p
+        .subcommands
+        .iter()
+        .map(|command| command.get_name().to_string())
+        .chain(std::iter::once("help".to_string()))
+        .collect()
+}
 
-            match ids_result {
-                Ok(ids) => logger::delete_logs(&config, ids),
-                Err(_) => eprintln!("Invalid ID found. Please provide numeric IDs or 'last'."),
-            }
-        }
-        ("edit", Some(sub_matches)) => {
-            let id_str = sub_matches.value_of("id").unwrap();
-            let content = sub_matches.value_of("content").unwrap().to_string();
-            let tags = sub_matches.value_of("tags").map(|t| {
-                t.split(',')
-                    .map(|s| s.trim().to_string())
-                    .filter(|s| !s.is_empty())
-                    .collect()
-            });
+/// The filter options shared by every command that reads entries.
+fn filter_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    let mut args = entry_filter_args();
+    args.extend(scope_args());
+    args
+}
 
-            let id_result = if id_str == "last" {
-                Ok(logger::get_log_count(&config))
-            } else {
-                id_str.parse::<usize>()
-            };
+/// The options picking the log, the archive or both.
+fn scope_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![
+        Arg::with_name("archive")
+            .short("a")
+            .long("archive")
+            .help("Read from archive file instead of main log"),
+        Arg::with_name("all")
+            .long("all")
+            .help("Read the archive and the main log together (archived IDs are shown as a:ID)")
+            .conflicts_with("archive"),
+    ]
+}
 
-            match id_result {
-                Ok(id) => logger::update_log(&config, id, content, tags),
-                Err(_) => eprintln!("Invalid ID: {}", id_str),
-            }
-        }
-        ("copy", Some(sub_matches)) => {
-            let id_str = sub_matches.value_of("id").unwrap();
-            let content = sub_matches.value_of("content").map(|s| s.to_string());
+fn parse_scope(sub_matches: &ArgMatches) -> Scope {
+    if sub_matches.is_present("all") {
+        Scope::All
+    } else if sub_matches.is_present("archive") {
+        Scope::Archive
+    } else {
+        Scope::Active
+    }
+}
 
-            let id_result = if id_str == "last" {
-                Ok(logger::get_log_count(&config))
-            } else {
-                id_str.parse::<usize>()
-            };
+/// The options selecting entries by date, tag or content.
+fn entry_filter_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    let mut args = untagged_filter_args();
+    args.push(
+        Arg::with_name("tags")
+            .short("t")
+            .long("tags")
+            .value_name("TAGS")
+            .help("Filter logs by tags (comma-separated)")
+            .takes_value(true),
+    );
+    args
+}
 
-            match id_result {
-                Ok(id) => logger::copy_log(&config, id, content),
-                Err(_) => eprintln!("Invalid ID: {}", id_str),
-            }
-        }
-        ("tags", Some(sub_matches)) => {
-            let use_archive = sub_matches.is_present("archive");
-            tags::list_tags(&config, use_archive);
-        }
-        ("archive", Some(sub_matches)) => {
-            let days = sub_matches
-                .value_of("days")
-                .unwrap_or("7")
-                .parse::<i64>()
-                .expect("Invalid days. Please provide a number.");
-            logger::archive_logs(&config, days);
+/// [`entry_filter_args`] without `--tags`, for `edit`, where `--tags` sets
+/// the tags.
+fn untagged_filter_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![
+        Arg::with_name("date")
+            .short("d")
+            .long("date")
+            .value_name("PERIOD")
+            .help("Filter logs by date or period (YYYY-MM-DD, today, this-week, 2026-W41, 2026-10, ...)")
+            .takes_value(true)
+            .conflicts_with_all(&["from", "to"]),
+        Arg::with_name("from")
+            .long("from")
+            .value_name("PERIOD")
+            .help("Only logs on or after the start of this date or period")
+            .takes_value(true),
+        Arg::with_name("to")
+            .long("to")
+            .value_name("PERIOD")
+            .help("Only logs on or before the end of this date or period")
+            .takes_value(true),
+        Arg::with_name("range")
+            .short("r")
+            .long("range")
+            .value_name("DAYS")
+            .help("Filter logs by date range (in days)")
+            .takes_value(true),
+        Arg::with_name("search")
+            .short("s")
+            .long("search")
+            .value_name("KEYWORD")
+            .help("Filter logs by keyword")
+            .takes_value(true),
+        Arg::with_name("query")
+            .short("q")
+            .long("query")
+            .value_name("QUERY")
+            .help("Filter logs by a query, e.g. 'tag:work and not (tag:break or content:lunch)'")
+            .takes_value(true),
+    ]
+}
+
+/// `--note` for commands that write an entry.
+fn note_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("note")
+        .short("n")
+        .long("note")
+        .value_name("TEXT")
+        .help("Notes to keep with the entry, as long as needed; - reads them from stdin (with edit, an empty value removes them)")
+        .takes_value(true)
+        .allow_hyphen_values(true)
+}
+
+/// The value of `--note`, read from `stdin` when it is `-`.
+fn parse_note(sub_matches: &ArgMatches, stdin: &mut dyn Read) -> Result<Option<String>, String> {
+    let notes = match sub_matches.value_of("note") {
+        Some("-") => {
+            let mut notes = String::new();
+            stdin
+                .read_to_string(&mut notes)
+                .map_err(|e| format!("Unable to read the notes from stdin: {}", e))?;
+            notes
         }
-        ("mdt", Some(sub_matches)) => {
-            let date = sub_matches.value_of("date").map(|d| d.to_string());
-            let range = sub_matches
-                .value_of("range")
-                .map(|r| r.parse::<i64>().expect("Invalid range"));
-            let tags: Vec<String> = sub_matches
-                .value_of("tags")
-                .unwrap_or("")
-                .split(',')
-                .map(|s| s.trim().to_string())
-                .filter(|s| !s.is_empty())
-                .collect();
-            let search = sub_matches.value_of("search").map(|s| s.to_string());
-            let use_archive = sub_matches.is_present("archive");
-            markdown::output_markdown_table(&config, date, range, tags, search, use_archive);
+        Some(notes) => notes.to_string(),
+        None => return Ok(None),
+    };
+    Ok(Some(notes.trim_end().to_string()))
+}
+
+/// `--output` for commands that can emit machine-readable results.
+fn output_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("output")
+        .short("o")
+        .long("output")
+        .value_name("FORMAT")
+        .help("Output format: a table for reading, or json/jsonl for scripts (see README for the schema; default: output from the config, table unless set)")
+        .possible_values(&["table", "json", "jsonl"])
+}
+
+/// The options shared by the commands that change tags across the log.
+fn tag_change_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![
+        Arg::with_name("archive")
+            .short("a")
+            .long("archive")
+            .help("Change archived entries too, not only the main log"),
+        Arg::with_name("dry-run")
+            .long("dry-run")
+            .help("Show which entries would change without changing them"),
+    ]
+}
+
+fn depth_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("depth")
+        .long("depth")
+        .value_name("N")
+        .help("Roll hierarchical tags (work/clientA/backend) up to N levels")
+        .takes_value(true)
+}
+
+fn parse_depth(sub_matches: &ArgMatches) -> Result<Option<usize>, String> {
+    match sub_matches.value_of("depth") {
+        Some(depth) => match depth.parse::<usize>() {
+            Ok(depth) if depth > 0 => Ok(Some(depth)),
+            _ => Err(format!("Invalid depth: {} (expected a number from 1)", depth)),
+        },
+        None => Ok(None),
+    }
+}
+
+fn parse_output(config: &Config, sub_matches: &ArgMatches) -> OutputFormat {
+    // possible_values guarantees the value parses
+    sub_matches
+        .value_of("output")
+        .map(|format| format.parse().unwrap())
+        .unwrap_or(config.output)
+}
+
+fn parse_dates(config: &Config, sub_matches: &ArgMatches) -> Result<Option<DateRange>, String> {
+    let today = Local::now().date_naive();
+    let period = |name: &str| {
+        sub_matches
+            .value_of(name)
+            .map(|p| parse_period(p, today, config.week_start))
+            .transpose()
+    };
+
+    if let Some((from, to)) = period("date")? {
+        return Ok(Some(DateRange {
+            from: Some(from),
+            to: Some(to),
+        }));
+    }
+
+    let from = period("from")?.map(|(start, _)| start);
+    let to = period("to")?.map(|(_, end)| end);
+    if let (Some(f), Some(t)) = (from, to) {
+        if f > t {
+            return Err(format!("--from ({}) is after --to ({}).", f, t));
         }
-        _ => {
-            println!("No subcommand was used");
+    }
+
+    if from.is_none() && to.is_none() {
+        Ok(None)
+    } else {
+        Ok(Some(DateRange { from, to }))
+    }
+}
+
+fn parse_filter(config: &Config, sub_matches: &ArgMatches) -> Result<Filter, String> {
+    Ok(Filter {
+        dates: parse_dates(config, sub_matches)?,
+        range: sub_matches
+            .value_of("range")
+            .map(|r| r.parse::<i64>().map_err(|_| format!("Invalid range: {}", r)))
+            .transpose()?,
+        tags: sub_matches
+            .value_of("tags")
+            .unwrap_or("")
+            .split(',')
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .map(|s| config.resolve_tag(s))
+            .collect(),
+        search: sub_matches.value_of("search").map(|s| s.to_string()),
+        query: sub_matches
+            .value_of("query")
+            .map(|q| Query::parse(q, Local::now(), config))
+            .transpose()?,
+    })
+}
+
+fn parse_at(sub_matches: &ArgMatches) -> Result<Option<DateTime<Local>>, String> {
+    sub_matches
+        .value_of("at")
+        .map(|at| parse_timestamp(at, Local::now()))
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_commands() {
+        let names = commands(&build_cli());
+        for name in ["log", "list", "migrate-archive", "help"] {
+            assert!(names.iter().any(|n| n == name), "{} is missing", name);
         }
+        assert!(!names.iter().any(|n| n == "standup"));
+    }
+
+    #[test]
+    fn test_parse_note() {
+        let note = |args: &[&str], stdin: &str| {
+            let matches = build_cli().get_matches_from(args);
+            let (_, sub_matches) = matches.subcommand();
+            parse_note(sub_matches.unwrap(), &mut stdin.as_bytes())
+        };
+        assert_eq!(note(&["acty", "log", "Coffee"], "ignored").unwrap(), None);
+        assert_eq!(
+            note(&["acty", "log", "Coffee", "--note", "with Sam\n"], "ignored").unwrap(),
+            Some("with Sam".to_string())
+        );
+        assert_eq!(
+            note(&["acty", "log", "Coffee", "-n", "-"], "- roadmap\n- hiring\n\n").unwrap(),
+            Some("- roadmap\n- hiring".to_string())
+        );
+        // An empty note is kept, so edit can remove the notes with it
+        assert_eq!(
+            note(&["acty", "edit", "last", "--note", ""], "").unwrap(),
+            Some(String::new())
+        );
     }
 }
@@ -1,18 +1,390 @@
-use serde::Deserialize;
+use crate::output::OutputFormat;
+use crate::util::{tag_matches, TAG_SEPARATOR};
+use chrono::format::{Item, StrftimeItems};
+use chrono::Weekday;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::PathBuf;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// JSON lines in `log_file` (the default).
+    #[default]
+    Json,
+    /// A SQLite database at `database_file`.
+    Sqlite,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ArchiveLayout {
+    /// Everything in one `archive.json` (the default).
+    #[default]
+    Single,
+    /// One gzip-compressed file per month in an `archive/` directory.
+    Monthly,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorMode {
+    /// Colors when writing to a terminal and `NO_COLOR` isn't set.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// The logbook used when none is picked, kept at the top-level `log_file`.
+pub const MAIN_BOOK: &str = "main";
+
+/// A named logbook from the `[books]` table. Anything left out is inherited
+/// from the top level, except where the log is kept.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Book {
+    /// Defaults to `books/<name>/action_log.json` next to the main log.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub log_file: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backend: Option<Backend>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub database_file: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub archive_dir: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub archive_layout: Option<ArchiveLayout>,
+}
+
+// Unknown keys are rejected so a typo doesn't silently fall back to a default.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
 pub struct Config {
     pub log_file: String,
+    pub backend: Backend,
+    /// Defaults to `log_file` with a `.db` extension.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub database_file: Option<String>,
+    /// Where the JSON backend keeps archived entries; defaults to the
+    /// directory of `log_file`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub archive_dir: Option<String>,
+    /// How the JSON backend stores archived entries.
+    pub archive_layout: ArchiveLayout,
+    /// What `acty archive` uses when no number of days is given.
+    pub archive_days: i64,
+    /// Added to every new entry.
+    pub default_tags: Vec<String>,
+    /// First day of `this-week`/`last-week`.
+    #[serde(
+        serialize_with = "serialize_weekday",
+        deserialize_with = "deserialize_weekday"
+    )]
+    pub week_start: Weekday,
+    /// How days are shown, as a strftime pattern.
+    pub date_format: String,
+    /// How entry times are shown in tables, as a strftime pattern.
+    pub time_format: String,
+    pub color: ColorMode,
+    /// The `--output` format used when none is given.
+    pub output: OutputFormat,
+    /// Used when neither `--book` nor `ACTY_BOOK` picks one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_book: Option<String>,
+    /// Shortcuts for commands and their arguments, e.g.
+    /// `standup = "list --from yesterday -t work"`.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub aliases: BTreeMap<String, String>,
+    /// Tags replaced whenever tags are entered, e.g. `wrk = "work"`.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub tag_aliases: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub books: BTreeMap<String, Book>,
+    /// The logbook the settings above were resolved for.
+    #[serde(skip)]
+    pub book: String,
+    /// The file the settings were read from, if there was one.
+    #[serde(skip)]
+    pub source: Option<PathBuf>,
+    /// The `ACTY_*` variables that overrode settings.
+    #[serde(skip)]
+    pub overrides: Vec<String>,
+}
+
+/// Settings that can be overridden by an `ACTY_<NAME>` environment variable.
+const ENV_OVERRIDES: [&str; 13] = [
+    "log_file",
+    "backend",
+    "database_file",
+    "archive_dir",
+    "archive_layout",
+    "archive_days",
+    "default_tags",
+    "week_start",
+    "date_format",
+    "time_format",
+    "color",
+    "output",
+    "default_book",
+];
+
+fn serialize_weekday<S>(weekday: &Weekday, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let name = match weekday {
+        Weekday::Mon => "monday",
+        Weekday::Tue => "tuesday",
+        Weekday::Wed => "wednesday",
+        Weekday::Thu => "thursday",
+        Weekday::Fri => "friday",
+        Weekday::Sat => "saturday",
+        Weekday::Sun => "sunday",
+    };
+    serializer.serialize_str(name)
+}
+
+fn deserialize_weekday<'de, D>(deserializer: D) -> Result<Weekday, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+    s.parse::<Weekday>()
+        .map_err(|_| serde::de::Error::custom(format!("invalid weekday '{}'", s)))
 }
 
 impl Config {
-    pub fn from_file(file_path: &str) -> Result<Self, Box<dyn std::error::Error>> {
-        let contents = fs::read_to_string(file_path)?;
-        let config: Config = toml::from_str(&contents)?;
+    /// Where the config is read from unless `--config` or `ACTY_CONFIG`
+    /// says otherwise.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::home_dir().map(|home| home.join(".config/acty/config.toml"))
+    }
+
+    /// Reads the config from `path`, or from [`Config::default_path`], and
+    /// applies `ACTY_*` overrides. Only a missing file at the default
+    /// location is fine; everything else is reported.
+    pub fn load(path: Option<&str>) -> Result<Config, String> {
+        Config::load_with(path, |name| std::env::var(name).ok())
+    }
+
+    /// [`Config::load`] with the `ACTY_*` overrides looked up through `env`.
+    pub fn load_with(
+        path: Option<&str>,
+        env: impl Fn(&str) -> Option<String>,
+    ) -> Result<Config, String> {
+        let (path, explicit) = match path {
+            Some(path) => (Some(PathBuf::from(path)), true),
+            None => (Config::default_path(), false),
+        };
+
+        let mut source = None;
+        let mut table = toml::value::Table::new();
+        if let Some(path) = path {
+            match fs::read_to_string(&path) {
+                Ok(contents) => {
+                    // Parsed as a Config first for errors that point at the line
+                    toml::from_str::<Config>(&contents)
+                        .map_err(|e| format!("Invalid config file {}: {}", path.display(), e))?;
+                    table = toml::from_str(&contents).expect("The config parsed a moment ago");
+                    source = Some(path);
+                }
+                Err(e) if explicit || e.kind() != std::io::ErrorKind::NotFound => {
+                    return Err(format!("Unable to read config file {}: {}", path.display(), e));
+                }
+                Err(_) => {}
+            }
+        }
+
+        let mut overrides = Vec::new();
+        for key in ENV_OVERRIDES {
+            let name = format!("ACTY_{}", key.to_uppercase());
+            let value = match env(&name) {
+                Some(value) => value,
+                None => continue,
+            };
+            let value = match key {
+                "archive_days" => toml::Value::Integer(value.trim().parse().map_err(|_| {
+                    format!("Invalid {}: expected a number of days, got '{}'", name, value)
+                })?),
+                "default_tags" => toml::Value::Array(
+                    value
+                        .split(',')
+                        .map(|tag| tag.trim())
+                        .filter(|tag| !tag.is_empty())
+                        .map(|tag| toml::Value::String(tag.to_string()))
+                        .collect(),
+                ),
+                _ => toml::Value::String(value),
+            };
+            table.insert(key.to_string(), value);
+            overrides.push(name);
+        }
+
+        let mut config: Config = toml::Value::Table(table)
+            .try_into()
+            .map_err(|e| format!("Invalid {}: {}", overrides.join("/"), e))?;
+        config.source = source;
+        config.overrides = overrides;
+        config.validate()?;
         Ok(config)
     }
+
+    fn validate(&self) -> Result<(), String> {
+        for (key, format) in [("date_format", &self.date_format), ("time_format", &self.time_format)] {
+            if StrftimeItems::new(format).any(|item| item == Item::Error) {
+                return Err(format!("Invalid {} '{}': not a valid strftime pattern", key, format));
+            }
+        }
+        if self.archive_days < 0 {
+            return Err(format!("Invalid archive_days {}: must not be negative", self.archive_days));
+        }
+        for (alias, tag) in &self.tag_aliases {
+            for name in [alias, tag] {
+                if name.trim().is_empty()
+                    || name.contains(',')
+                    || name.trim() != name
+                    || name.ends_with(TAG_SEPARATOR)
+                {
+                    return Err(format!("Invalid tag alias {} = '{}': tags can't be empty, end with '/', contain commas or surrounding spaces", alias, tag));
+                }
+            }
+            if self.tag_aliases.keys().any(|other| tag_matches(tag, other)) {
+                return Err(format!("Invalid tag alias {} = '{}': '{}' is an alias itself", alias, tag, tag));
+            }
+        }
+        Ok(())
+    }
+
+    /// `tag` with its alias expanded: with `wrk = "work"`, `wrk` becomes
+    /// `work` and `wrk/api` becomes `work/api`.
+    pub fn resolve_tag(&self, tag: &str) -> String {
+        // The most specific alias wins
+        let alias = self
+            .tag_aliases
+            .keys()
+            .filter(|alias| tag_matches(tag, alias))
+            .max_by_key(|alias| alias.len());
+        match alias {
+            Some(alias) => format!("{}{}", self.tag_aliases[alias], &tag[alias.len()..]),
+            None => tag.to_string(),
+        }
+    }
+
+    /// [`Config::resolve_tag`] for each tag, dropping the duplicates that
+    /// leaves.
+    pub fn resolve_tags(&self, tags: Vec<String>) -> Vec<String> {
+        let mut resolved: Vec<String> = Vec::new();
+        for tag in tags {
+            let tag = self.resolve_tag(&tag);
+            if !resolved.contains(&tag) {
+                resolved.push(tag);
+            }
+        }
+        resolved
+    }
+
+    /// Whether table output should be colored.
+    pub fn use_color(&self) -> bool {
+        match self.color {
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+            ColorMode::Auto => std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none(),
+        }
+    }
+
+    /// The settings for logbook `name`, or for the one picked by
+    /// `default_book` (or the main log) when `name` is `None`.
+    pub fn for_book(&self, name: Option<&str>) -> Result<Config, String> {
+        let name = name
+            .or(self.default_book.as_deref())
+            .unwrap_or(MAIN_BOOK);
+        let config = self.book(name).ok_or_else(|| {
+            format!(
+                "Unknown book '{}'. Books: {}.",
+                name,
+                self.book_names().join(", ")
+            )
+        })?;
+
+        // JSON logs keep their archive and trash next to the log file
+        for other in self.book_names() {
+            let other_config = match self.book(&other) {
+                Some(c) if other != name => c,
+                _ => continue,
+            };
+            if config.backend == Backend::Json
+                && other_config.backend == Backend::Json
+                && Path::new(&other_config.log_file).parent() == Path::new(&config.log_file).parent()
+            {
+                return Err(format!(
+                    "Books '{}' and '{}' keep their logs in the same directory; give each its own.",
+                    other, name
+                ));
+            }
+        }
+        Ok(config)
+    }
+
+    /// The settings for logbook `name` without checking them against the
+    /// other books, or `None` if there is no such book.
+    pub fn book(&self, name: &str) -> Option<Config> {
+        let mut config = self.clone();
+        config.book = name.to_string();
+        let book = match self.books.get(name) {
+            Some(book) => book,
+            None if name == MAIN_BOOK => return Some(config),
+            None => return None,
+        };
+
+        config.log_file = match &book.log_file {
+            Some(log_file) => log_file.clone(),
+            None => Path::new(&self.log_file)
+                .with_file_name("books")
+                .join(name)
+                .join("action_log.json")
+                .to_string_lossy()
+                .into_owned(),
+        };
+        config.database_file = book.database_file.clone();
+        config.archive_dir = book.archive_dir.clone();
+        config.backend = book.backend.unwrap_or(self.backend);
+        config.archive_layout = book.archive_layout.unwrap_or(self.archive_layout);
+        Some(config)
+    }
+
+    /// Every logbook, starting with the main one.
+    pub fn book_names(&self) -> Vec<String> {
+        let mut names = vec![MAIN_BOOK.to_string()];
+        names.extend(self.books.keys().filter(|n| *n != MAIN_BOOK).cloned());
+        names
+    }
+
+    pub fn database_path(&self) -> PathBuf {
+        match &self.database_file {
+            Some(path) => PathBuf::from(path),
+            None => Path::new(&self.log_file).with_extension("db"),
+        }
+    }
+}
+
+/// Prints the settings in effect, as TOML, noting where they came from.
+pub fn show_config(config: &Config) {
+    match (&config.source, Config::default_path()) {
+        (Some(path), _) => println!("# Read from {}", path.display()),
+        (None, Some(path)) => println!("# No config file at {}; using defaults", path.display()),
+        (None, None) => println!("# No config file; using defaults"),
+    }
+    if !config.overrides.is_empty() {
+        println!("# Overridden by {}", config.overrides.join(", "));
+    }
+    println!("# Book: {}", config.book);
+    print!(
+        "{}",
+        toml::to_string_pretty(config).expect("Unable to serialize the config")
+    );
 }
 
 impl Default for Config {
@@ -23,6 +395,90 @@ impl Default for Config {
 
         Config {
             log_file: log_file.to_string_lossy().into_owned(),
+            backend: Backend::default(),
+            database_file: None,
+            archive_dir: None,
+            week_start: Weekday::Mon,
+            archive_layout: ArchiveLayout::default(),
+            archive_days: 7,
+            default_tags: Vec::new(),
+            date_format: "%Y-%m-%d".to_string(),
+            time_format: "%Y-%m-%d %H:%M".to_string(),
+            color: ColorMode::default(),
+            output: OutputFormat::default(),
+            default_book: None,
+            aliases: BTreeMap::new(),
+            tag_aliases: BTreeMap::new(),
+            books: BTreeMap::new(),
+            book: MAIN_BOOK.to_string(),
+            source: None,
+            overrides: Vec::new(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load() {
+        let test_dir = std::env::temp_dir().join("acty_test_config");
+        let _ = fs::remove_dir_all(&test_dir);
+        fs::create_dir_all(&test_dir).unwrap();
+        let path = test_dir.join("config.toml");
+        let path_str = path.to_string_lossy().to_string();
+
+        fs::write(
+            &path,
+            "log_file = \"/logs/action_log.json\"\nweek_start = \"sunday\"\ndefault_tags = [\"work\"]\n",
+        )
+        .unwrap();
+        let load = |path: &str| Config::load_with(Some(path), |_| None);
+        let config = Config::load_with(Some(&path_str), |name| {
+            (name == "ACTY_ARCHIVE_DAYS").then(|| "30".to_string())
+        })
+        .unwrap();
+        assert_eq!(config.log_file, "/logs/action_log.json");
+        assert_eq!(config.week_start, Weekday::Sun);
+        assert_eq!(config.default_tags, vec!["work"]);
+        assert_eq!(config.archive_days, 30);
+        assert_eq!(config.overrides, vec!["ACTY_ARCHIVE_DAYS"]);
+        assert_eq!(config.source, Some(path.clone()));
+        let bad_days = Config::load_with(Some(&path_str), |name| {
+            (name == "ACTY_ARCHIVE_DAYS").then(|| "soon".to_string())
+        });
+        assert!(bad_days.unwrap_err().contains("ACTY_ARCHIVE_DAYS"));
+
+        // Typos and bad values are reported rather than ignored
+        fs::write(&path, "log_flie = \"/logs/action_log.json\"\n").unwrap();
+        assert!(load(&path_str).unwrap_err().contains("log_flie"));
+        fs::write(&path, "time_format = \"%Q\"\n").unwrap();
+        assert!(load(&path_str).is_err());
+        fs::write(&path, "[tag_aliases]\nwrk = \"work\"\nwork = \"job\"\n").unwrap();
+        assert!(load(&path_str).unwrap_err().contains("alias itself"));
+        // A file that was asked for explicitly has to exist
+        assert!(load(&test_dir.join("missing.toml").to_string_lossy()).is_err());
+
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+
+    #[test]
+    fn test_resolve_tags() {
+        let config = Config {
+            tag_aliases: [("wrk", "work"), ("wrk/a", "work/clientA")]
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            ..Config::default()
+        };
+        assert_eq!(config.resolve_tag("wrk"), "work");
+        assert_eq!(config.resolve_tag("wrk/api"), "work/api");
+        assert_eq!(config.resolve_tag("wrk/a/ui"), "work/clientA/ui");
+        assert_eq!(config.resolve_tag("wrkshop"), "wrkshop");
+        assert_eq!(
+            config.resolve_tags(vec!["wrk".to_string(), "home".to_string(), "work".to_string()]),
+            vec!["work", "home"]
+        );
+    }
+}
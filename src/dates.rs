@@ -0,0 +1,291 @@
+use crate::util::parse_duration;
+use chrono::{
+    DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, TimeZone,
+    Weekday,
+};
+
+/// An inclusive range of days; either end may be open.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateRange {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+impl DateRange {
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        self.from.is_none_or(|from| date >= from) && self.to.is_none_or(|to| date <= to)
+    }
+}
+
+/// The first day of the week containing `day`.
+pub fn start_of_week(day: NaiveDate, week_start: Weekday) -> NaiveDate {
+    let offset =
+        (7 + day.weekday().num_days_from_monday() - week_start.num_days_from_monday()) % 7;
+    day - Duration::days(offset as i64)
+}
+
+/// Parses a named period into its first and last day:
+///
+/// * `today`, `yesterday`
+/// * `this-week`, `last-week` (weeks begin on `week_start`)
+/// * `this-month`, `last-month`, `this-year`, `last-year`
+/// * `2026-10-17`, `2026-10`, `2026`, and ISO weeks such as `2026-W41`
+pub fn parse_period(
+    input: &str,
+    today: NaiveDate,
+    week_start: Weekday,
+) -> Result<(NaiveDate, NaiveDate), String> {
+    let input = input.trim();
+    let lower = input.to_lowercase();
+    let week_of = |day: NaiveDate| {
+        let start = start_of_week(day, week_start);
+        (start, start + Duration::days(6))
+    };
+
+    match lower.as_str() {
+        "today" => return Ok((today, today)),
+        "yesterday" => {
+            let day = today - Duration::days(1);
+            return Ok((day, day));
+        }
+        "this-week" => return Ok(week_of(today)),
+        "last-week" => return Ok(week_of(today - Duration::days(7))),
+        "this-month" => return month(today.year(), today.month(), input),
+        "last-month" => {
+            let first = NaiveDate::from_ymd_opt(today.year(), today.month(), 1).unwrap();
+            let previous = first - Duration::days(1);
+            return month(previous.year(), previous.month(), input);
+        }
+        "this-year" => return year(today.year(), input),
+        "last-year" => return year(today.year() - 1, input),
+        _ => {}
+    }
+
+    if let Ok(date) = NaiveDate::parse_from_str(input, "%Y-%m-%d") {
+        return Ok((date, date));
+    }
+
+    if let Some((y, w)) = lower.split_once("-w") {
+        if let (Ok(y), Ok(w)) = (y.parse::<i32>(), w.parse::<u32>()) {
+            let start = NaiveDate::from_isoywd_opt(y, w, Weekday::Mon)
+                .ok_or_else(|| format!("Invalid week: '{}'.", input))?;
+            return Ok((start, start + Duration::days(6)));
+        }
+    }
+
+    if let Some((y, m)) = lower.split_once('-') {
+        if let (Ok(y), Ok(m)) = (y.parse::<i32>(), m.parse::<u32>()) {
+            return month(y, m, input);
+        }
+    }
+
+    if lower.len() == 4 {
+        if let Ok(y) = lower.parse::<i32>() {
+            return year(y, input);
+        }
+    }
+
+    Err(format!(
+        "Invalid date or period: '{}'. Use e.g. 2026-10-17, today, yesterday, this-week, last-week, this-month, 2026-W41 or 2026-10.",
+        input
+    ))
+}
+
+fn month(y: i32, m: u32, input: &str) -> Result<(NaiveDate, NaiveDate), String> {
+    let first =
+        NaiveDate::from_ymd_opt(y, m, 1).ok_or_else(|| format!("Invalid month: '{}'.", input))?;
+    let next = if m == 12 {
+        NaiveDate::from_ymd_opt(y + 1, 1, 1)
+    } else {
+        NaiveDate::from_ymd_opt(y, m + 1, 1)
+    }
+    .ok_or_else(|| format!("Invalid month: '{}'.", input))?;
+    Ok((first, next - Duration::days(1)))
+}
+
+fn year(y: i32, input: &str) -> Result<(NaiveDate, NaiveDate), String> {
+    match (NaiveDate::from_ymd_opt(y, 1, 1), NaiveDate::from_ymd_opt(y, 12, 31)) {
+        (Some(first), Some(last)) => Ok((first, last)),
+        _ => Err(format!("Invalid year: '{}'.", input)),
+    }
+}
+
+/// Parses the time expressions accepted by `--at`:
+///
+/// * RFC 3339 (`2026-10-17T17:30:00+09:00`)
+/// * a local date and/or time (`2026-10-17 17:30`, `2026-10-17`, `17:30`)
+/// * a day name with an optional time (`yesterday 17:30`, `today 9:00`, `mon 14:00`)
+/// * an offset from now (`-15m`, `+1h`, `1h30m ago`, `now`)
+pub fn parse_timestamp(input: &str, now: DateTime<Local>) -> Result<DateTime<Local>, String> {
+    let input = input.trim();
+    let lower = input.to_lowercase();
+
+    if lower == "now" {
+        return Ok(now);
+    }
+
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(input) {
+        return Ok(datetime.with_timezone(&Local));
+    }
+
+    for format in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M"] {
+        if let Ok(naive) = NaiveDateTime::parse_from_str(input, format) {
+            return to_local(naive, input);
+        }
+    }
+
+    if let Ok(date) = NaiveDate::parse_from_str(input, "%Y-%m-%d") {
+        return to_local(date.and_time(NaiveTime::MIN), input);
+    }
+
+    // Offsets that don't parse or reach past the range of dates are invalid
+    // times, not panics
+    let shift = |offset: &str, forward: bool| {
+        parse_duration(offset)
+            .ok()
+            .and_then(TimeDelta::try_seconds)
+            .and_then(|delta| match forward {
+                true => now.checked_add_signed(delta),
+                false => now.checked_sub_signed(delta),
+            })
+            .ok_or_else(|| invalid(input))
+    };
+    if let Some(offset) = lower.strip_prefix('-') {
+        return shift(offset, false);
+    }
+    if let Some(offset) = lower.strip_prefix('+') {
+        return shift(offset, true);
+    }
+    if let Some(offset) = lower.strip_suffix("ago") {
+        return shift(offset, false);
+    }
+
+    if let Some(time) = parse_time_of_day(&lower) {
+        return to_local(now.date_naive().and_time(time), input);
+    }
+
+    let mut words = lower.split_whitespace();
+    let day = words.next().unwrap_or_default();
+    let time = words.next();
+    if words.next().is_none() {
+        if let Some(date) = parse_day_name(day, now.date_naive()) {
+            let time = match time {
+                Some(t) => parse_time_of_day(t).ok_or_else(|| invalid(input))?,
+                None => now.time(),
+            };
+            return to_local(date.and_time(time), input);
+        }
+    }
+
+    Err(invalid(input))
+}
+
+fn invalid(input: &str) -> String {
+    format!(
+        "Invalid time: '{}'. Use e.g. 17:30, 'yesterday 17:30', -15m or 2026-10-17T17:30:00+09:00.",
+        input
+    )
+}
+
+fn to_local(naive: NaiveDateTime, input: &str) -> Result<DateTime<Local>, String> {
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .ok_or_else(|| format!("'{}' does not exist in the local time zone.", input))
+}
+
+fn parse_time_of_day(input: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(input, "%H:%M:%S")
+        .or_else(|_| NaiveTime::parse_from_str(input, "%H:%M"))
+        .ok()
+}
+
+/// `today`, `yesterday`, `tomorrow`, or a weekday name meaning its most
+/// recent occurrence before today.
+fn parse_day_name(input: &str, today: NaiveDate) -> Option<NaiveDate> {
+    match input {
+        "today" => return Some(today),
+        "yesterday" => return Some(today - Duration::days(1)),
+        "tomorrow" => return Some(today + Duration::days(1)),
+        _ => {}
+    }
+
+    let weekday = input.parse::<Weekday>().ok()?;
+    let mut days_back = (7 + today.weekday().num_days_from_monday()
+        - weekday.num_days_from_monday())
+        % 7;
+    if days_back == 0 {
+        days_back = 7;
+    }
+    Some(today - Duration::days(days_back as i64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn now() -> DateTime<Local> {
+        // A Wednesday
+        Local.with_ymd_and_hms(2026, 10, 14, 12, 0, 0).unwrap()
+    }
+
+    fn at(y: i32, m: u32, d: u32, h: u32, min: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(y, m, d, h, min, 0).unwrap()
+    }
+
+    #[test]
+    fn test_parse_timestamp() {
+        assert_eq!(parse_timestamp("now", now()), Ok(now()));
+        assert_eq!(parse_timestamp("17:30", now()), Ok(at(2026, 10, 14, 17, 30)));
+        assert_eq!(parse_timestamp("yesterday 17:30", now()), Ok(at(2026, 10, 13, 17, 30)));
+        assert_eq!(parse_timestamp("Yesterday", now()), Ok(at(2026, 10, 13, 12, 0)));
+        assert_eq!(parse_timestamp("mon 9:00", now()), Ok(at(2026, 10, 12, 9, 0)));
+        assert_eq!(parse_timestamp("wed 9:00", now()), Ok(at(2026, 10, 7, 9, 0)));
+        assert_eq!(parse_timestamp("-15m", now()), Ok(at(2026, 10, 14, 11, 45)));
+        assert_eq!(parse_timestamp("1h30m ago", now()), Ok(at(2026, 10, 14, 10, 30)));
+        assert_eq!(parse_timestamp("+1h", now()), Ok(at(2026, 10, 14, 13, 0)));
+        assert_eq!(parse_timestamp("2026-10-01 08:15", now()), Ok(at(2026, 10, 1, 8, 15)));
+        assert_eq!(parse_timestamp("2026-10-01", now()), Ok(at(2026, 10, 1, 0, 0)));
+
+        let rfc = parse_timestamp("2026-10-01T08:15:00+00:00", now()).unwrap();
+        assert_eq!(
+            rfc.with_timezone(&chrono::Utc).to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            "2026-10-01T08:15:00Z"
+        );
+
+        assert!(parse_timestamp("someday", now()).is_err());
+        assert!(parse_timestamp("yesterday 25:00", now()).is_err());
+        assert!(parse_timestamp("-15x", now()).is_err());
+        for huge in ["-1e300", "-99999999d", "+99999999w", "1e300 ago"] {
+            assert!(parse_timestamp(huge, now()).unwrap_err().starts_with("Invalid time"), "{}", huge);
+        }
+    }
+
+    #[test]
+    fn test_parse_period() {
+        // A Wednesday
+        let today = NaiveDate::from_ymd_opt(2026, 10, 14).unwrap();
+        let d = |m: u32, day: u32| NaiveDate::from_ymd_opt(2026, m, day).unwrap();
+        let period = |input: &str| parse_period(input, today, Weekday::Mon);
+
+        assert_eq!(period("today"), Ok((d(10, 14), d(10, 14))));
+        assert_eq!(period("yesterday"), Ok((d(10, 13), d(10, 13))));
+        assert_eq!(period("this-week"), Ok((d(10, 12), d(10, 18))));
+        assert_eq!(period("last-week"), Ok((d(10, 5), d(10, 11))));
+        assert_eq!(period("this-month"), Ok((d(10, 1), d(10, 31))));
+        assert_eq!(period("last-month"), Ok((d(9, 1), d(9, 30))));
+        assert_eq!(period("2026-02"), Ok((d(2, 1), d(2, 28))));
+        assert_eq!(period("2026-W41"), Ok((d(10, 5), d(10, 11))));
+        assert_eq!(period("2026-10-03"), Ok((d(10, 3), d(10, 3))));
+        assert_eq!(period("2026"), Ok((d(1, 1), d(12, 31))));
+        assert_eq!(
+            parse_period("this-week", today, Weekday::Sun),
+            Ok((d(10, 11), d(10, 17)))
+        );
+
+        assert!(period("2026-13").is_err());
+        assert!(period("2026-W60").is_err());
+        assert!(period("next-week").is_err());
+        assert!(period("2026-10-32").is_err());
+    }
+}
@@ -0,0 +1,351 @@
+use crate::config::Config;
+use crate::log_entry::LogEntry;
+use crate::storage::{self, Storage, Store};
+use crate::util::Filter;
+use chrono::{DateTime, Local};
+use std::collections::{HashMap, HashSet};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Something wrong with the stored log, found by `doctor`.
+#[derive(Debug, Clone, PartialEq)]
+enum Problem {
+    /// A file that can't be read at all, e.g. a truncated archive partition.
+    Damaged(PathBuf, String),
+    /// A line (or database row) that isn't a valid entry.
+    Unreadable(String),
+    /// An entry stored before one with an earlier timestamp.
+    OutOfOrder(LogEntry),
+    /// An exact copy of the entry with the given ID.
+    Duplicate { entry: LogEntry, of: String },
+    /// A different entry reusing the ID of an earlier one.
+    IdClash(LogEntry),
+    EmptyTag(LogEntry),
+}
+
+impl Problem {
+    fn describe(&self, store: Store, time_format: &str) -> String {
+        let entry = |e: &LogEntry| {
+            format!(
+                "{} {} '{}'",
+                store.display_id(&e.id),
+                e.timestamp.format(time_format),
+                e.content
+            )
+        };
+        match self {
+            Problem::Damaged(path, error) => format!("damaged file: {} ({})", path.display(), error),
+            Problem::Unreadable(line) => {
+                let shown: String = line.chars().take(60).collect();
+                let more = if shown.len() < line.len() { "..." } else { "" };
+                format!("unreadable line: {}{}", shown, more)
+            }
+            Problem::OutOfOrder(e) => format!("out of order: {}", entry(e)),
+            Problem::Duplicate { entry: e, of } => format!("duplicate of {}: {}", of, entry(e)),
+            Problem::IdClash(e) => format!("ID used by another entry: {}", entry(e)),
+            Problem::EmptyTag(e) => format!("empty tag: {}", entry(e)),
+        }
+    }
+}
+
+/// The problems found in one store and how to repair them: the IDs to
+/// delete, the entries to insert in their place and the duplicates dropped.
+#[derive(Default)]
+struct Examination {
+    problems: Vec<Problem>,
+    delete: Vec<String>,
+    insert: Vec<LogEntry>,
+    removed: Vec<LogEntry>,
+}
+
+/// What has been seen in the stores examined so far, so duplicates are found
+/// across the archive and the log.
+#[derive(Default)]
+struct Seen {
+    ids: HashMap<String, (Store, LogEntry)>,
+    entries: HashMap<(DateTime<Local>, String, Vec<String>), String>,
+}
+
+/// Scans the log and the archive for problems, optionally repairing them.
+/// `quarantine` moves unreadable lines to a file next to the log and renames
+/// damaged files out of the way; `fix` does that too and repairs everything
+/// else. Dropped duplicates are kept in the quarantine file, so nothing is
+/// lost.
+pub fn doctor(config: &Config, fix: bool, quarantine: bool) {
+    let _lock = if fix || quarantine {
+        storage::lock(config)
+    } else {
+        storage::lock_shared(config)
+    };
+    let storage = storage::open(config);
+    let quarantine_path = storage::companion_path(config, "quarantine");
+
+    let mut seen = Seen::default();
+    let mut found = 0;
+    let mut quarantined: Vec<String> = Vec::new();
+    let mut set_aside: Vec<PathBuf> = Vec::new();
+    for store in [Store::Archive, Store::Active] {
+        let mut problems: Vec<Problem> = storage
+            .damaged(store)
+            .into_iter()
+            .map(|(path, error)| Problem::Damaged(path, error))
+            .collect();
+        if fix || quarantine {
+            set_aside.extend(storage.set_aside_damaged(store));
+        }
+        problems.extend(storage.unreadable(store).into_iter().map(Problem::Unreadable));
+        let examination = examine(storage.as_ref(), store, &mut seen);
+        problems.extend(examination.problems.iter().cloned());
+        for problem in &problems {
+            println!("{}: {}", store.name(), problem.describe(store, &config.time_format));
+        }
+        found += problems.len();
+
+        if fix || quarantine {
+            quarantined.extend(storage.remove_unreadable(store));
+        }
+        if fix {
+            storage.delete(store, &examination.delete);
+            storage.insert(store, &examination.insert);
+            quarantined.extend(examination.removed.iter().map(|e| {
+                serde_json::to_string(e).expect("Unable to serialize the log entry")
+            }));
+        }
+    }
+
+    if !quarantined.is_empty() {
+        let mut file = OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(&quarantine_path)
+            .expect("Unable to open the quarantine file");
+        for line in &quarantined {
+            writeln!(file, "{}", line).expect("Unable to write to the quarantine file");
+        }
+    }
+
+    if found == 0 {
+        println!("No problems found.");
+    } else if fix {
+        println!("Repaired {} problem(s).", found);
+    } else if quarantine {
+        println!("Found {} problem(s).", found);
+    } else {
+        println!(
+            "Found {} problem(s). Run `acty doctor --fix` to repair them, or `acty doctor --quarantine` to only set unreadable lines aside.",
+            found
+        );
+    }
+    if !quarantined.is_empty() {
+        println!(
+            "Moved {} line(s) to {}.",
+            quarantined.len(),
+            quarantine_path.display()
+        );
+    }
+    for path in &set_aside {
+        println!("Moved a damaged file to {}.", path.display());
+    }
+}
+
+fn examine(storage: &dyn Storage, store: Store, seen: &mut Seen) -> Examination {
+    let mut result = Examination::default();
+    // Entries that stay, with whether they changed
+    let mut kept: Vec<(LogEntry, LogEntry, bool)> = Vec::new();
+    // IDs shared by several entries; deleting them takes the first along
+    let mut shared: HashSet<String> = HashSet::new();
+
+    for entry in storage.read(store, &Filter::default()) {
+        let mut repaired = entry.clone();
+        let mut changed = false;
+
+        if let Some((first_store, first)) = seen.ids.get(&entry.id) {
+            if *first_store == store {
+                shared.insert(entry.id.clone());
+            }
+            if *first == entry {
+                result.problems.push(Problem::Duplicate {
+                    of: first_store.display_id(&first.id),
+                    entry: entry.clone(),
+                });
+                result.delete.push(entry.id.clone());
+                result.removed.push(entry);
+                continue;
+            }
+            result.problems.push(Problem::IdClash(entry.clone()));
+            result.delete.push(entry.id.clone());
+            repaired.reassign_id();
+            changed = true;
+        }
+
+        let mut tags = entry.tags.clone();
+        tags.sort();
+        let key = (entry.timestamp, entry.content.clone(), tags);
+        if let Some(of) = seen.entries.get(&key) {
+            result.problems.push(Problem::Duplicate {
+                entry: entry.clone(),
+                of: of.clone(),
+            });
+            result.delete.push(entry.id.clone());
+            result.removed.push(entry);
+            continue;
+        }
+
+        if entry.tags.iter().any(|tag| tag.trim().is_empty()) {
+            result.problems.push(Problem::EmptyTag(entry.clone()));
+            repaired.tags.retain(|tag| !tag.trim().is_empty());
+            changed = true;
+        }
+
+        seen.entries.insert(key, store.display_id(&repaired.id));
+        seen.ids
+            .entry(repaired.id.clone())
+            .or_insert((store, entry.clone()));
+        kept.push((entry, repaired, changed));
+    }
+
+    let misplaced = out_of_order(&kept.iter().map(|(e, _, _)| e.timestamp).collect::<Vec<_>>());
+    for (index, (entry, repaired, mut changed)) in kept.into_iter().enumerate() {
+        if misplaced.contains(&index) {
+            result.problems.push(Problem::OutOfOrder(entry.clone()));
+            changed = true;
+        }
+        if changed || shared.contains(&entry.id) {
+            result.delete.push(entry.id);
+            result.insert.push(repaired);
+        }
+    }
+    result
+}
+
+/// The positions of the timestamps that have to move for the rest to be in
+/// order: everything outside the longest run that already is.
+fn out_of_order(timestamps: &[DateTime<Local>]) -> HashSet<usize> {
+    // Patience sorting: `tails[k]` ends the best ordered run of length k + 1
+    let mut tails: Vec<usize> = Vec::new();
+    let mut previous: Vec<Option<usize>> = Vec::with_capacity(timestamps.len());
+    for (index, timestamp) in timestamps.iter().enumerate() {
+        let length = tails.partition_point(|&t| timestamps[t] <= *timestamp);
+        previous.push(length.checked_sub(1).map(|k| tails[k]));
+        if length == tails.len() {
+            tails.push(index);
+        } else {
+            tails[length] = index;
+        }
+    }
+
+    let mut ordered = HashSet::new();
+    let mut current = tails.last().copied();
+    while let Some(index) = current {
+        ordered.insert(index);
+        current = previous[index];
+    }
+    (0..timestamps.len()).filter(|i| !ordered.contains(i)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ArchiveLayout;
+    use crate::logger::{archive_logs, log_action};
+    use std::fs;
+
+    #[test]
+    fn test_out_of_order() {
+        let at = |hour: u32| {
+            DateTime::parse_from_rfc3339(&format!("2026-10-01T{:02}:00:00+00:00", hour))
+                .unwrap()
+                .with_timezone(&Local)
+        };
+        let misplaced = out_of_order(&[at(1), at(9), at(2), at(3), at(4)]);
+        assert_eq!(misplaced, HashSet::from([1]));
+        assert!(out_of_order(&[at(1), at(1), at(2)]).is_empty());
+    }
+
+    #[test]
+    fn test_doctor_fix() {
+        let test_dir = std::env::temp_dir().join("acty_test_doctor");
+        let _ = fs::remove_dir_all(&test_dir);
+        let log_path = test_dir.join("action_log.json");
+        let config = Config {
+            log_file: log_path.to_string_lossy().to_string(),
+            ..Config::default()
+        };
+
+        let old = Local::now() - chrono::Duration::days(10);
+        log_action(&config, "Archived".to_string(), vec![], None, Some(old), None);
+        archive_logs(&config, 7);
+        log_action(&config, "Latest".to_string(), vec![], None, None, None);
+        let archived = &storage::open(&config).read(Store::Archive, &Filter::default())[0];
+        let lines = [
+            "not json".to_string(),
+            serde_json::to_string(archived).unwrap(),
+            r#"{"id":"zzzzzzzz","timestamp":"2026-01-01T09:00:00+00:00","content":"Early","tags":["", "work"]}"#.to_string(),
+            r#"{"id":"zzzzzzzz","timestamp":"2026-01-02T09:00:00+00:00","content":"Clash","tags":[]}"#.to_string(),
+        ];
+        let mut contents = fs::read_to_string(&log_path).unwrap();
+        for line in &lines {
+            contents.push_str(line);
+            contents.push('\n');
+        }
+        fs::write(&log_path, contents).unwrap();
+
+        doctor(&config, true, false);
+
+        let active = storage::open(&config).read(Store::Active, &Filter::default());
+        let contents: Vec<&str> = active.iter().map(|e| e.content.as_str()).collect();
+        assert_eq!(contents, vec!["Early", "Clash", "Latest"]);
+        assert_eq!(active[0].id, "zzzzzzzz");
+        assert_eq!(active[0].tags, vec!["work"]);
+        assert_ne!(active[1].id, "zzzzzzzz");
+
+        let quarantined = fs::read_to_string(test_dir.join("action_log.json.quarantine")).unwrap();
+        assert_eq!(quarantined, format!("{}\n{}\n", lines[0], lines[1]));
+
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+
+    #[test]
+    fn test_doctor_damaged_partition() {
+        let test_dir = std::env::temp_dir().join("acty_test_doctor_damaged");
+        let _ = fs::remove_dir_all(&test_dir);
+        let config = Config {
+            log_file: test_dir.join("action_log.json").to_string_lossy().to_string(),
+            archive_layout: ArchiveLayout::Monthly,
+            ..Config::default()
+        };
+
+        let old = Local::now() - chrono::Duration::days(100);
+        log_action(&config, "Archived".to_string(), vec![], None, Some(old), None);
+        archive_logs(&config, 7);
+        let partition = fs::read_dir(test_dir.join("archive"))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .next()
+            .unwrap();
+        let compressed = fs::read(&partition).unwrap();
+        fs::write(&partition, &compressed[..compressed.len() / 2]).unwrap();
+
+        // Reads skip the damaged partition instead of failing.
+        let storage = storage::open(&config);
+        assert!(storage.read(Store::Archive, &Filter::default()).is_empty());
+        assert_eq!(storage.damaged(Store::Archive).len(), 1);
+        assert!(storage.damaged(Store::Active).is_empty());
+
+        doctor(&config, false, true);
+
+        assert!(!partition.exists());
+        assert_eq!(
+            fs::read(partition.with_file_name(format!(
+                "{}.damaged",
+                partition.file_name().unwrap().to_string_lossy()
+            )))
+            .unwrap(),
+            &compressed[..compressed.len() / 2]
+        );
+        assert!(storage.damaged(Store::Archive).is_empty());
+
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+}
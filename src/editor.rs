@@ -0,0 +1,448 @@
+use crate::config::Config;
+use crate::dates::parse_timestamp;
+use crate::journal::{self, Change};
+use crate::log_entry::LogEntry;
+use crate::logger::find_entry;
+use crate::storage::{self, Scope, Store};
+use crate::util::{sort_tags, Filter};
+use chrono::{DateTime, Local};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tempfile::NamedTempFile;
+
+/// Starts each entry's block in the file being edited.
+const HEADER: &str = "=== ";
+/// How times are written to the file; unlike `time_format`, it keeps the
+/// seconds and can always be read back.
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+const INSTRUCTIONS: &str = "\
+# Edit the entries below, then save and close the editor.
+# Each entry starts with its \"=== ID\" line, which must stay as it is.
+# `time:` takes anything --at does; `tags:` is comma-separated.
+# The first line after them is the content, any lines after that the notes.
+# Removing an entry's block leaves the entry unchanged.
+# Lines starting with # before the first entry are ignored.
+";
+
+/// One entry's block as it was saved.
+struct Block {
+    id: String,
+    line: usize,
+    time: Option<String>,
+    tags: Option<String>,
+    content: Vec<String>,
+}
+
+/// Opens the entry with `id`, or every entry the filter selects, in the
+/// user's editor and stores the changes once all of them are valid. The
+/// whole edit is one step in the undo history.
+pub fn edit_in_editor(config: &Config, id: Option<&str>, filter: Filter, scope: Scope) {
+    let entries = {
+        let _lock = storage::lock_shared(config);
+        let storage = storage::open(config);
+        match id {
+            Some(id) => match find_entry(storage.as_ref(), id) {
+                Ok(found) => vec![found],
+                Err(e) => {
+                    eprintln!("{}", e);
+                    return;
+                }
+            },
+            None => storage::read_scope(storage.as_ref(), scope, &filter),
+        }
+    };
+    if entries.is_empty() {
+        println!("No logs found.");
+        return;
+    }
+
+    let file = temp_file("acty-edit-");
+    std::fs::write(file.path(), render(&entries)).expect("Unable to write the file to edit");
+    if let Err(e) = open_editor(file.path()) {
+        eprintln!("{}", e);
+        return;
+    }
+    let edited = match read_edited(file.path()) {
+        Ok(edited) => edited,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+
+    let changes = match apply_edits(&entries, &edited, Local::now()) {
+        Ok(changes) => changes,
+        Err(errors) => {
+            for error in errors {
+                eprintln!("{}", error);
+            }
+            eprintln!("Nothing was changed. Your edits are in {}.", keep(file).display());
+            return;
+        }
+    };
+    // Expand tag aliases in the tags that were edited
+    let changes: Vec<(Store, LogEntry, LogEntry)> = changes
+        .into_iter()
+        .filter_map(|(store, before, mut after)| {
+            if after.tags != before.tags {
+                after.tags = config.resolve_tags(after.tags);
+                if sort_tags(after.tags.clone()) == sort_tags(before.tags.clone()) {
+                    after.tags = before.tags.clone();
+                }
+            }
+            (after != before).then_some((store, before, after))
+        })
+        .collect();
+    if changes.is_empty() {
+        println!("No changes.");
+        return;
+    }
+
+    let _lock = storage::lock(config);
+    let storage = storage::open(config);
+    // The editor may have been open for a while; don't clobber changes
+    // made in the meantime
+    for store in [Store::Active, Store::Archive] {
+        let current: HashMap<String, LogEntry> = storage
+            .read(store, &Filter::default())
+            .into_iter()
+            .map(|e| (e.id.clone(), e))
+            .collect();
+        for (_, before, _) in changes.iter().filter(|(s, _, _)| *s == store) {
+            if current.get(&before.id) != Some(before) {
+                eprintln!(
+                    "Log entry {} changed while it was being edited. Nothing was changed; your edits are in {}.",
+                    store.display_id(&before.id),
+                    keep(file).display()
+                );
+                return;
+            }
+        }
+    }
+
+    for store in [Store::Active, Store::Archive] {
+        let updated: Vec<LogEntry> = changes
+            .iter()
+            .filter(|(s, _, _)| *s == store)
+            .map(|(_, _, after)| after.clone())
+            .collect();
+        if !updated.is_empty() {
+            storage.update_all(store, &updated);
+        }
+    }
+    let count = changes.len();
+    let command = match id {
+        Some(id) => format!("edit {} --editor", id),
+        None => "edit --editor".to_string(),
+    };
+    journal::record(
+        config,
+        &command,
+        changes
+            .into_iter()
+            .map(|(store, before, after)| Change::Update {
+                store,
+                before: Box::new(before),
+                after: Box::new(after),
+            })
+            .collect(),
+    );
+
+    println!("Updated {} log entry(ies).", count);
+}
+
+/// Runs `$VISUAL` or `$EDITOR` (`vi` if neither is set) on `path` and waits
+/// for it. The variable may include arguments, e.g. `code --wait`.
+pub fn open_editor(path: &Path) -> Result<(), String> {
+    let editor = std::env::var("VISUAL")
+        .ok()
+        .filter(|e| !e.trim().is_empty())
+        .or_else(|| std::env::var("EDITOR").ok().filter(|e| !e.trim().is_empty()))
+        .unwrap_or_else(|| "vi".to_string());
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$1\"", editor))
+        .arg(&editor)
+        .arg(path)
+        .status()
+        .map_err(|e| format!("Unable to start the editor '{}': {}", editor, e))?;
+    if !status.success() {
+        return Err(format!(
+            "The editor '{}' failed ({}); nothing was changed.",
+            editor, status
+        ));
+    }
+    Ok(())
+}
+
+/// A new file, with a name no one else can guess, for the user to edit. It is
+/// removed when dropped unless it is kept.
+fn temp_file(prefix: &str) -> NamedTempFile {
+    tempfile::Builder::new()
+        .prefix(prefix)
+        .suffix(".txt")
+        .tempfile()
+        .expect("Unable to create the file to edit")
+}
+
+/// Keeps a file the user edited after acty exits, so their edits aren't
+/// lost, and returns where it is.
+fn keep(file: NamedTempFile) -> PathBuf {
+    file.into_temp_path()
+        .keep()
+        .expect("Unable to keep the edited file")
+}
+
+/// Reads back the edited file. The editor may have moved or deleted it.
+fn read_edited(path: &Path) -> Result<String, String> {
+    std::fs::read_to_string(path).map_err(|e| {
+        format!(
+            "Unable to read the edited file {} ({}); nothing was changed.",
+            path.display(),
+            e
+        )
+    })
+}
+
+/// Lets the user write a new entry's content (the first line) and notes
+/// (the lines after it) in their editor, starting from what was given on the
+/// command line. Returns `None` if they left it empty.
+pub fn compose(
+    content: Option<&str>,
+    notes: Option<&str>,
+) -> Result<Option<(String, Option<String>)>, String> {
+    let file = temp_file("acty-note-");
+    std::fs::write(file.path(), body(content.unwrap_or_default(), notes))
+        .expect("Unable to write the file to edit");
+    open_editor(file.path())?;
+    let written = read_edited(file.path())?;
+
+    let lines: Vec<String> = written.lines().map(|line| line.to_string()).collect();
+    Ok(split_body(&lines))
+}
+
+/// The content followed by the notes, if any, after a blank line.
+fn body(content: &str, notes: Option<&str>) -> String {
+    match notes {
+        Some(notes) => format!("{}\n\n{}\n", content, notes),
+        None => format!("{}\n", content),
+    }
+}
+
+/// Splits written text into the content, its first non-blank line, and the
+/// notes: the rest, without the blank lines around it.
+fn split_body(lines: &[String]) -> Option<(String, Option<String>)> {
+    let mut lines = lines.iter().skip_while(|line| line.trim().is_empty());
+    let content = lines.next()?.trim().to_string();
+    let rest: Vec<&str> = lines
+        .map(|line| line.trim_end())
+        .skip_while(|line| line.is_empty())
+        .collect();
+    let notes = match rest.iter().rposition(|line| !line.is_empty()) {
+        Some(last) => Some(rest[..=last].join("\n")),
+        None => None,
+    };
+    Some((content, notes))
+}
+
+fn render(entries: &[(Store, LogEntry)]) -> String {
+    let mut text = INSTRUCTIONS.to_string();
+    for (store, entry) in entries {
+        text.push_str(&format!(
+            "\n{}{}\ntime: {}\ntags: {}\n{}",
+            HEADER,
+            store.display_id(&entry.id),
+            entry.timestamp.format(TIME_FORMAT),
+            sort_tags(entry.tags.clone()).join(", "),
+            body(&entry.content, entry.notes.as_deref())
+        ));
+    }
+    text
+}
+
+fn parse(text: &str) -> (Vec<Block>, Vec<String>) {
+    let mut blocks: Vec<Block> = Vec::new();
+    let mut errors = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let number = index + 1;
+        if let Some(id) = line.strip_prefix(HEADER) {
+            blocks.push(Block {
+                id: id.trim().to_string(),
+                line: number,
+                time: None,
+                tags: None,
+                content: Vec::new(),
+            });
+            continue;
+        }
+        let block = match blocks.last_mut() {
+            Some(block) => block,
+            None => {
+                if !line.trim().is_empty() && !line.starts_with('#') {
+                    errors.push(format!("line {}: text before the first entry", number));
+                }
+                continue;
+            }
+        };
+
+        // The fields come before the content
+        if block.content.is_empty() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let (None, Some(time)) = (&block.time, line.strip_prefix("time:")) {
+                block.time = Some(time.trim().to_string());
+                continue;
+            }
+            if let (None, Some(tags)) = (&block.tags, line.strip_prefix("tags:")) {
+                block.tags = Some(tags.to_string());
+                continue;
+            }
+        }
+        block.content.push(line.to_string());
+    }
+    (blocks, errors)
+}
+
+/// Checks the edited text against the entries it was made from, and returns
+/// each changed entry with its store, as it was and as it is now. All
+/// problems are reported at once.
+fn apply_edits(
+    entries: &[(Store, LogEntry)],
+    text: &str,
+    now: DateTime<Local>,
+) -> Result<Vec<(Store, LogEntry, LogEntry)>, Vec<String>> {
+    let originals: HashMap<String, &(Store, LogEntry)> = entries
+        .iter()
+        .map(|found| (found.0.display_id(&found.1.id), found))
+        .collect();
+    let (blocks, mut errors) = parse(text);
+    let mut seen = HashSet::new();
+    let mut changes = Vec::new();
+
+    for block in blocks {
+        let (store, before) = match originals.get(&block.id) {
+            Some((store, entry)) => (*store, entry),
+            None => {
+                errors.push(format!(
+                    "line {}: '{}' is not one of the entries being edited",
+                    block.line, block.id
+                ));
+                continue;
+            }
+        };
+        if !seen.insert(block.id.clone()) {
+            errors.push(format!("line {}: {} appears twice", block.line, block.id));
+            continue;
+        }
+        let mut after = before.clone();
+
+        if let Some(time) = &block.time {
+            if *time != before.timestamp.format(TIME_FORMAT).to_string() {
+                match parse_timestamp(time, now) {
+                    Ok(timestamp) => after.timestamp = timestamp,
+                    Err(e) => errors.push(format!("line {}: {}: {}", block.line, block.id, e)),
+                }
+            }
+        }
+
+        if let Some(tags) = &block.tags {
+            let mut unique: Vec<String> = Vec::new();
+            for tag in tags.split(',').map(|t| t.trim()).filter(|t| !t.is_empty()) {
+                if !unique.iter().any(|u| u == tag) {
+                    unique.push(tag.to_string());
+                }
+            }
+            // Only a different set of tags is a change, not a new order
+            if sort_tags(unique.clone()) != sort_tags(before.tags.clone()) {
+                after.tags = unique;
+            }
+        }
+
+        match split_body(&block.content) {
+            Some((content, notes)) => {
+                after.content = content;
+                after.notes = notes;
+            }
+            None => errors.push(format!("line {}: {} has no content", block.line, block.id)),
+        }
+
+        if after != *before {
+            changes.push((store, before.clone(), after));
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(changes)
+    } else {
+        Err(errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_edits() {
+        let now = Local::now();
+        let entry = |content: &str, tags: &[&str]| {
+            LogEntry::new(now, content.to_string(), tags.iter().map(|t| t.to_string()).collect())
+        };
+        let entries = vec![
+            (Store::Archive, entry("Standup", &["work"])),
+            (Store::Active, entry("Coffee", &["break", "cafe"])),
+            (Store::Active, entry("Deploy", &["ops"])),
+        ];
+        let text = render(&entries);
+        assert!(apply_edits(&entries, &text, now).unwrap().is_empty());
+
+        // Retag the first, reword and annotate the second, drop the third's
+        // block
+        let archived = Store::Archive.display_id(&entries[0].1.id);
+        let edited = text
+            .replace("tags: work\n", "tags: work, meeting, work\n")
+            .replace("Coffee\n", "Coffee with Sam\n\n  - roadmap\n  - hiring\n\n");
+        let edited = &edited[..edited.find(&entries[2].1.id).unwrap() - HEADER.len()];
+        let changes = apply_edits(&entries, edited, now).unwrap();
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].0, Store::Archive);
+        assert_eq!(changes[0].2.id, entries[0].1.id);
+        assert_eq!(changes[0].2.tags, vec!["work", "meeting"]);
+        assert_eq!(changes[1].2.content, "Coffee with Sam");
+        assert_eq!(changes[1].2.notes.as_deref(), Some("  - roadmap\n  - hiring"));
+        assert_eq!(changes[1].2.tags, entries[1].1.tags);
+
+        // Every problem is reported and nothing is returned to apply
+        let broken = format!(
+            "{}\n=== nope\ntime: now\ntags:\nX\n",
+            text.replace("Deploy\n", "\n")
+                .replacen("time: ", "time: whenever ", 1)
+        );
+        let errors = apply_edits(&entries, &broken, now).unwrap_err();
+        assert_eq!(errors.len(), 3, "{:?}", errors);
+        assert!(errors[0].contains(&archived));
+        assert!(errors[1].contains("no content"));
+        assert!(errors[2].contains("nope"));
+    }
+
+    #[test]
+    fn test_temp_file() {
+        let (first, second) = (temp_file("acty-edit-"), temp_file("acty-edit-"));
+        assert_ne!(first.path(), second.path());
+        let path = first.path().to_path_buf();
+        std::fs::write(&path, "Coffee\n").unwrap();
+        assert_eq!(read_edited(&path).unwrap(), "Coffee\n");
+
+        // An editor that removes the file means nothing was changed
+        drop(first);
+        assert!(!path.exists());
+        assert!(read_edited(&path).unwrap_err().contains("nothing was changed"));
+
+        let kept = keep(second);
+        assert!(kept.exists());
+        std::fs::remove_file(kept).unwrap();
+    }
+}
@@ -0,0 +1,88 @@
+use crate::config::Config;
+use crate::log_entry::LogEntry;
+use crate::storage::{self, Scope};
+use crate::util::{sort_tags, Filter};
+use std::io::Write;
+
+/// Columns written by `export --format csv`, in order. `import` accepts the
+/// same names.
+pub const CSV_COLUMNS: [&str; 6] = ["id", "timestamp", "duration_seconds", "tags", "content", "notes"];
+
+pub fn export_logs(config: &Config, filter: Filter, scope: Scope, format: &str) {
+    let entries: Vec<LogEntry> = {
+        let _lock = storage::lock_shared(config);
+        storage::read_scope(storage::open(config).as_ref(), scope, &filter)
+            .into_iter()
+            .map(|(_, entry)| entry)
+            .collect()
+    };
+
+    let stdout = std::io::stdout();
+    let result = match format {
+        "csv" => write_csv(&entries, stdout.lock()),
+        other => {
+            eprintln!("Unsupported export format: {}", other);
+            return;
+        }
+    };
+
+    if let Err(e) = result {
+        eprintln!("Unable to export logs: {}", e);
+    }
+}
+
+pub fn write_csv<W: Write>(entries: &[LogEntry], writer: W) -> Result<(), csv::Error> {
+    let mut csv_writer = csv::Writer::from_writer(writer);
+    csv_writer.write_record(CSV_COLUMNS)?;
+
+    for entry in entries {
+        let duration = if entry.running {
+            String::new()
+        } else {
+            entry.duration.map(|d| d.to_string()).unwrap_or_default()
+        };
+        csv_writer.write_record([
+            entry.id.clone(),
+            entry.timestamp.to_rfc3339(),
+            duration,
+            sort_tags(entry.tags.clone()).join(","),
+            entry.content.clone(),
+            entry.notes.clone().unwrap_or_default(),
+        ])?;
+    }
+
+    csv_writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Local, TimeZone};
+
+    #[test]
+    fn test_write_csv() {
+        let mut entry = LogEntry::new(
+            Local.with_ymd_and_hms(2026, 10, 1, 9, 30, 0).unwrap(),
+            "Review, then \"ship\"".to_string(),
+            vec!["work".to_string(), "ops".to_string()],
+        );
+        entry.duration = Some(1500);
+        entry.notes = Some("Checked\nthe logs".to_string());
+
+        let mut output = Vec::new();
+        write_csv(std::slice::from_ref(&entry), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let (header, row) = output.split_once('\n').unwrap();
+
+        assert_eq!(header, "id,timestamp,duration_seconds,tags,content,notes");
+        assert_eq!(
+            row,
+            format!(
+                "{},{},1500,\"ops,work\",\"Review, then \"\"ship\"\"\",\"Checked\nthe logs\"\n",
+                entry.id,
+                entry.timestamp.to_rfc3339()
+            )
+        );
+    }
+}
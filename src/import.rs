@@ -0,0 +1,310 @@
+use crate::config::Config;
+use crate::dates::parse_timestamp;
+use crate::journal::{self, Change};
+use crate::log_entry::{is_valid_id, LogEntry};
+use crate::storage::{self, Store};
+use crate::util::{parse_duration, Filter, MAX_DURATION};
+use chrono::{DateTime, Local};
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+
+/// Fields an imported column can be mapped to.
+const FIELDS: [&str; 7] = [
+    "id", "timestamp", "duration_seconds", "duration", "tags", "content", "notes",
+];
+
+/// The outcome of reading a CSV file: entries ready to store and a message
+/// for every rejected line.
+pub struct ImportResult {
+    pub entries: Vec<LogEntry>,
+    pub rejected: Vec<String>,
+}
+
+pub fn import_logs(config: &Config, path: &str, mapping: HashMap<String, String>, dry_run: bool) {
+    let file = match std::fs::File::open(path) {
+        Ok(f) => f,
+        Err(e) => {
+            eprintln!("Unable to open {}: {}", path, e);
+            return;
+        }
+    };
+
+    let _lock = storage::lock(config);
+    let storage = storage::open(config);
+    let existing_ids: HashSet<String> = storage
+        .read(Store::Active, &Filter::default())
+        .into_iter()
+        .chain(storage.read(Store::Archive, &Filter::default()))
+        .map(|e| e.id)
+        .collect();
+
+    let mut result = match parse_csv(file, &mapping, &existing_ids, Local::now()) {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+
+    for entry in &mut result.entries {
+        entry.tags = config.resolve_tags(std::mem::take(&mut entry.tags));
+    }
+
+    for message in &result.rejected {
+        eprintln!("Rejected {}", message);
+    }
+
+    if dry_run {
+        println!(
+            "Dry run: {} entry(ies) would be imported, {} line(s) rejected.",
+            result.entries.len(),
+            result.rejected.len()
+        );
+        return;
+    }
+
+    if !result.entries.is_empty() {
+        storage.insert(Store::Active, &result.entries);
+        journal::record(
+            config,
+            &format!("import {}", path),
+            vec![Change::Add {
+                store: Store::Active,
+                entries: result.entries.clone(),
+            }],
+        );
+    }
+    println!(
+        "Imported {} entry(ies), rejected {} line(s).",
+        result.entries.len(),
+        result.rejected.len()
+    );
+}
+
+/// Reads log entries from CSV. Columns are matched to fields by name
+/// (case-insensitive) unless `mapping` assigns a field to another column.
+/// `timestamp` and `content` are required; `tags` are comma separated;
+/// `duration_seconds` is in seconds while `duration` takes forms like `25m`;
+/// `notes` may span several lines.
+pub fn parse_csv<R: Read>(
+    reader: R,
+    mapping: &HashMap<String, String>,
+    existing_ids: &HashSet<String>,
+    now: DateTime<Local>,
+) -> Result<ImportResult, String> {
+    for field in mapping.keys() {
+        if !FIELDS.contains(&field.as_str()) {
+            return Err(format!(
+                "Unknown field '{}' in column mapping (expected one of: {}).",
+                field,
+                FIELDS.join(", ")
+            ));
+        }
+    }
+
+    let mut csv_reader = csv::ReaderBuilder::new().flexible(true).from_reader(reader);
+    let headers = csv_reader
+        .headers()
+        .map_err(|e| format!("Unable to read CSV header: {}", e))?
+        .clone();
+
+    let column = |field: &str| -> Result<Option<usize>, String> {
+        let name = mapping.get(field).map(|s| s.as_str()).unwrap_or(field);
+        let index = headers.iter().position(|h| h.trim().eq_ignore_ascii_case(name));
+        if index.is_none() && mapping.contains_key(field) {
+            return Err(format!("Column '{}' mapped to {} not found in the header.", name, field));
+        }
+        Ok(index)
+    };
+
+    let id_col = column("id")?;
+    let timestamp_col = column("timestamp")?
+        .ok_or("The CSV file needs a 'timestamp' column (or map one with --map timestamp=COLUMN).")?;
+    let content_col = column("content")?
+        .ok_or("The CSV file needs a 'content' column (or map one with --map content=COLUMN).")?;
+    let seconds_col = column("duration_seconds")?;
+    let duration_col = column("duration")?;
+    let tags_col = column("tags")?;
+    let notes_col = column("notes")?;
+
+    let mut seen_ids: HashSet<String> = existing_ids.clone();
+    let mut entries = Vec::new();
+    let mut rejected = Vec::new();
+
+    for record in csv_reader.records() {
+        let record = match record {
+            Ok(r) => r,
+            Err(e) => {
+                let line = e.position().map(|p| p.line()).unwrap_or(0);
+                rejected.push(format!("line {}: {}", line, e));
+                continue;
+            }
+        };
+        let line = record.position().map(|p| p.line()).unwrap_or(0);
+        let cell = |index: Option<usize>| -> &str {
+            index.and_then(|i| record.get(i)).unwrap_or("").trim()
+        };
+
+        let timestamp = match parse_timestamp(cell(Some(timestamp_col)), now) {
+            Ok(t) => t,
+            Err(e) => {
+                rejected.push(format!("line {}: {}", line, e));
+                continue;
+            }
+        };
+
+        let content = cell(Some(content_col)).to_string();
+        if content.is_empty() {
+            rejected.push(format!("line {}: content is empty", line));
+            continue;
+        }
+
+        let seconds = cell(seconds_col);
+        let duration = cell(duration_col);
+        let duration = if !seconds.is_empty() {
+            match seconds.parse::<i64>() {
+                Ok(s) if (0..=MAX_DURATION).contains(&s) => Some(s),
+                _ => {
+                    rejected.push(format!("line {}: invalid duration_seconds '{}'", line, seconds));
+                    continue;
+                }
+            }
+        } else if !duration.is_empty() {
+            match parse_duration(duration) {
+                Ok(s) => Some(s),
+                Err(e) => {
+                    rejected.push(format!("line {}: {}", line, e));
+                    continue;
+                }
+            }
+        } else {
+            None
+        };
+
+        let mut tags: Vec<String> = Vec::new();
+        for tag in cell(tags_col).split(',').map(|t| t.trim()) {
+            if !tag.is_empty() && !tags.iter().any(|t| t == tag) {
+                tags.push(tag.to_string());
+            }
+        }
+
+        let mut entry = LogEntry::new(timestamp, content, tags);
+        entry.duration = duration;
+        entry.notes = Some(cell(notes_col).to_string()).filter(|n| !n.is_empty());
+
+        // Keep exported IDs so re-importing an export is detected; IDs from
+        // other tools that don't look like acty's are replaced.
+        let id = cell(id_col).to_lowercase();
+        if is_valid_id(&id) {
+            if seen_ids.contains(&id) {
+                rejected.push(format!("line {}: an entry with ID {} already exists", line, id));
+                continue;
+            }
+            entry.id = id;
+        }
+        seen_ids.insert(entry.id.clone());
+        entries.push(entry);
+    }
+
+    Ok(ImportResult { entries, rejected })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::write_csv;
+
+    #[test]
+    fn test_parse_csv_validates_rows() {
+        let input = "\
+Date,Task,Labels,duration
+2026-10-01 09:00,Standup,\"work, meeting\",15m
+yesterday,,work,
+not a date,Lunch,,
+2026-10-01 13:00,Review,work,soon
+2026-10-01 14:00,Deploy,,
+";
+        let mapping: HashMap<String, String> = [
+            ("timestamp".to_string(), "Date".to_string()),
+            ("content".to_string(), "task".to_string()),
+            ("tags".to_string(), "Labels".to_string()),
+        ]
+        .into_iter()
+        .collect();
+
+        let result = parse_csv(input.as_bytes(), &mapping, &HashSet::new(), Local::now()).unwrap();
+
+        assert_eq!(result.entries.len(), 2);
+        assert_eq!(result.entries[0].content, "Standup");
+        assert_eq!(result.entries[0].tags, vec!["work", "meeting"]);
+        assert_eq!(result.entries[0].duration, Some(900));
+        assert_eq!(result.entries[1].content, "Deploy");
+
+        assert_eq!(result.rejected.len(), 3);
+        assert!(result.rejected[0].starts_with("line 3: content is empty"));
+        assert!(result.rejected[1].starts_with("line 4: Invalid time"));
+        assert!(result.rejected[2].starts_with("line 5: Invalid duration"));
+    }
+
+    #[test]
+    fn test_parse_csv_checks_ids_and_durations() {
+        let input = "\
+id,timestamp,content,duration_seconds,duration
+k3x9ab01,2026-10-01 09:00,Kept,60,
+last,2026-10-01 10:00,Renamed,,
+a:k3x9ab02,2026-10-01 11:00,Renamed,,
+\"k3x9 b03\",2026-10-01 12:00,Renamed,,
+12345678,2026-10-01 13:00,Renamed,,
+k3x9ab04,2026-10-01 14:00,Forever,9223372036854775807,
+k3x9ab05,2026-10-01 15:00,Forever,,1e300
+";
+        let result =
+            parse_csv(input.as_bytes(), &HashMap::new(), &HashSet::new(), Local::now()).unwrap();
+
+        let ids: Vec<&str> = result.entries.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids.len(), 5);
+        assert_eq!(ids[0], "k3x9ab01");
+        for (id, original) in ids[1..].iter().zip(["last", "a:k3x9ab02", "k3x9 b03", "12345678"]) {
+            assert_ne!(*id, original);
+            assert!(is_valid_id(id));
+        }
+
+        assert_eq!(result.rejected.len(), 2);
+        assert!(result.rejected[0].starts_with("line 7: invalid duration_seconds"));
+        assert!(result.rejected[1].starts_with("line 8: Invalid duration"));
+    }
+
+    #[test]
+    fn test_export_round_trip() {
+        let mut entry = LogEntry::new(Local::now(), "Exported".to_string(), vec!["a".to_string()]);
+        entry.duration = Some(60);
+        entry.notes = Some("Line one\nLine two".to_string());
+        let mut csv = Vec::new();
+        write_csv(std::slice::from_ref(&entry), &mut csv).unwrap();
+
+        let result = parse_csv(csv.as_slice(), &HashMap::new(), &HashSet::new(), Local::now()).unwrap();
+        assert_eq!(result.entries.len(), 1);
+        assert_eq!(result.entries[0].id, entry.id);
+        assert_eq!(result.entries[0].timestamp, entry.timestamp);
+        assert_eq!(result.entries[0].duration, Some(60));
+        assert_eq!(result.entries[0].notes, entry.notes);
+
+        // Importing the same export again is rejected
+        let existing: HashSet<String> = [entry.id.clone()].into_iter().collect();
+        let again = parse_csv(csv.as_slice(), &HashMap::new(), &existing, Local::now()).unwrap();
+        assert!(again.entries.is_empty());
+        assert_eq!(again.rejected.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_csv_requires_columns() {
+        let err = parse_csv("when,what\n".as_bytes(), &HashMap::new(), &HashSet::new(), Local::now())
+            .err()
+            .unwrap();
+        assert!(err.contains("'timestamp' column"));
+
+        let mapping: HashMap<String, String> =
+            [("colour".to_string(), "x".to_string())].into_iter().collect();
+        assert!(parse_csv("a\n".as_bytes(), &mapping, &HashSet::new(), Local::now()).is_err());
+    }
+}
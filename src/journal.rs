@@ -0,0 +1,495 @@
+use crate::config::Config;
+use crate::log_entry::{local_date_time, LogEntry};
+use crate::storage::{self, replace_file, Storage, Store};
+use crate::util::Filter;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// How many operations the journal remembers at least; older ones can't be
+/// undone. Operations are appended, and the journal is cut back to this many
+/// once every `JOURNAL_LIMIT` operations.
+const JOURNAL_LIMIT: usize = 100;
+
+/// One reversible change to the log, carrying the entries involved so it can
+/// be replayed in either direction.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum Change {
+    Add {
+        store: Store,
+        entries: Vec<LogEntry>,
+    },
+    Remove {
+        store: Store,
+        entries: Vec<LogEntry>,
+    },
+    Update {
+        store: Store,
+        before: Box<LogEntry>,
+        after: Box<LogEntry>,
+    },
+    Move {
+        from: Store,
+        to: Store,
+        entries: Vec<LogEntry>,
+    },
+}
+
+impl Change {
+    fn inverse(&self) -> Change {
+        match self.clone() {
+            Change::Add { store, entries } => Change::Remove { store, entries },
+            Change::Remove { store, entries } => Change::Add { store, entries },
+            Change::Update {
+                store,
+                before,
+                after,
+            } => Change::Update {
+                store,
+                before: after,
+                after: before,
+            },
+            Change::Move { from, to, entries } => Change::Move {
+                from: to,
+                to: from,
+                entries,
+            },
+        }
+    }
+
+    /// Makes sure the log is still in the state this change was made from,
+    /// so replaying it can't clobber later edits.
+    fn check(&self, storage: &dyn Storage) -> Result<(), String> {
+        let store = match self {
+            Change::Add { store, .. } | Change::Remove { store, .. } | Change::Update { store, .. } => *store,
+            Change::Move { from, .. } => *from,
+        };
+        // One read however many entries the change holds
+        let current: HashMap<String, LogEntry> = storage
+            .read(store, &Filter::default())
+            .into_iter()
+            .map(|e| (e.id.clone(), e))
+            .collect();
+        let expect_present = |entries: &[LogEntry]| {
+            for entry in entries {
+                if current.get(&entry.id) != Some(entry) {
+                    return Err(format!("entry {} has changed since", entry.id));
+                }
+            }
+            Ok(())
+        };
+
+        match self {
+            Change::Add { entries, .. } => {
+                for entry in entries {
+                    if current.contains_key(&entry.id) {
+                        return Err(format!("entry {} already exists", entry.id));
+                    }
+                }
+                Ok(())
+            }
+            Change::Remove { entries, .. } | Change::Move { entries, .. } => expect_present(entries),
+            Change::Update { before, .. } => expect_present(std::slice::from_ref(before)),
+        }
+    }
+
+    fn apply(&self, storage: &dyn Storage) {
+        match self {
+            Change::Add { store, entries } => storage.insert(*store, entries),
+            Change::Remove { store, entries } => {
+                storage.delete(*store, &ids(entries));
+            }
+            Change::Update { store, after, .. } => {
+                storage.update(*store, after);
+            }
+            Change::Move { from, to, entries } => {
+                storage.move_entries(*from, *to, &ids(entries));
+            }
+        }
+    }
+
+    /// The change without the entries in `ids`, or `None` if nothing is left.
+    fn without(self, ids: &[String]) -> Option<Change> {
+        let keep = |entries: Vec<LogEntry>| -> Option<Vec<LogEntry>> {
+            let entries: Vec<LogEntry> = entries.into_iter().filter(|e| !ids.contains(&e.id)).collect();
+            (!entries.is_empty()).then_some(entries)
+        };
+        match self {
+            Change::Add { store, entries } => keep(entries).map(|entries| Change::Add { store, entries }),
+            Change::Remove { store, entries } => keep(entries).map(|entries| Change::Remove { store, entries }),
+            Change::Update { before, .. } if ids.contains(&before.id) => None,
+            update @ Change::Update { .. } => Some(update),
+            Change::Move { from, to, entries } => keep(entries).map(|entries| Change::Move { from, to, entries }),
+        }
+    }
+
+    /// A short summary for `history`, or `None` for bookkeeping such as the
+    /// deletion time stamped on trashed entries.
+    fn describe(&self) -> Option<String> {
+        let count = |entries: &[LogEntry]| match entries {
+            [entry] => format!("{} '{}'", entry.id, entry.content),
+            _ => format!("{} entries", entries.len()),
+        };
+        let place = |store: &Store| match store {
+            Store::Active => "the log",
+            Store::Archive => "the archive",
+            Store::Trash => "the trash",
+        };
+        Some(match self {
+            Change::Add { store, entries } => {
+                format!("added {} to {}", count(entries), place(store))
+            }
+            Change::Remove { store, entries } => {
+                format!("removed {} from {}", count(entries), place(store))
+            }
+            Change::Update { store: Store::Trash, .. } => return None,
+            Change::Update { after, .. } => format!("changed {}", after.id),
+            Change::Move { to, entries, .. } => {
+                format!("moved {} to {}", count(entries), place(to))
+            }
+        })
+    }
+}
+
+fn ids(entries: &[LogEntry]) -> Vec<String> {
+    entries.iter().map(|e| e.id.clone()).collect()
+}
+
+/// A command that changed the log, as recorded in the journal.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Operation {
+    /// Counts up with every operation recorded; 0 in older journals.
+    #[serde(default)]
+    pub seq: u64,
+    #[serde(with = "local_date_time")]
+    pub time: DateTime<Local>,
+    pub command: String,
+    pub changes: Vec<Change>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub undone: bool,
+}
+
+impl Operation {
+    fn describe(&self) -> String {
+        self.changes
+            .iter()
+            .filter_map(|c| c.describe())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+fn journal_path(config: &Config) -> PathBuf {
+    storage::companion_path(config, "history")
+}
+
+fn load(config: &Config) -> Vec<Operation> {
+    std::fs::read_to_string(journal_path(config))
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+fn save(config: &Config, operations: &[Operation]) {
+    let mut contents = String::new();
+    for operation in operations {
+        contents.push_str(&serde_json::to_string(operation).expect("Unable to serialize the journal"));
+        contents.push('\n');
+    }
+    replace_file(&journal_path(config), &contents);
+}
+
+/// Records a command's changes so it can be undone. Anything undone before
+/// can no longer be redone. Call this while holding the exclusive lock.
+///
+/// Usually only the last operation is read and the new one appended; the
+/// whole journal is rewritten only to drop undone operations or to trim it.
+pub fn record(config: &Config, command: &str, changes: Vec<Change>) {
+    if changes.is_empty() {
+        return;
+    }
+
+    let path = journal_path(config);
+    // Undone operations are always the last ones
+    let last: Option<Operation> = last_line(&path).and_then(|line| serde_json::from_str(&line).ok());
+    let operation = Operation {
+        seq: last.as_ref().map_or(1, |op| op.seq + 1),
+        time: Local::now(),
+        command: command.to_string(),
+        changes,
+        undone: false,
+    };
+
+    if last.as_ref().is_some_and(|op| op.undone) || operation.seq.is_multiple_of(JOURNAL_LIMIT as u64) {
+        let mut operations = load(config);
+        operations.retain(|op| !op.undone);
+        operations.push(operation);
+        let excess = operations.len().saturating_sub(JOURNAL_LIMIT);
+        operations.drain(..excess);
+        save(config, &operations);
+        return;
+    }
+
+    let mut line = serde_json::to_string(&operation).expect("Unable to serialize the journal");
+    line.push('\n');
+    let mut file = OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(&path)
+        .expect("Unable to open the journal");
+    file.write_all(line.as_bytes()).expect("Unable to write to the journal");
+}
+
+/// The last non-empty line of the file at `path`, read from the end so the
+/// rest of the file is left alone.
+fn last_line(path: &Path) -> Option<String> {
+    const CHUNK: u64 = 64 * 1024;
+    let mut file = File::open(path).ok()?;
+    let mut start = file.seek(SeekFrom::End(0)).ok()?;
+    let mut tail: Vec<u8> = Vec::new();
+    loop {
+        let end = tail.iter().rposition(|&b| b != b'\n').map_or(0, |i| i + 1);
+        if let Some(newline) = tail[..end].iter().rposition(|&b| b == b'\n') {
+            return String::from_utf8(tail[newline + 1..end].to_vec()).ok();
+        }
+        if start == 0 {
+            return match end {
+                0 => None,
+                _ => String::from_utf8(tail[..end].to_vec()).ok(),
+            };
+        }
+        let from = start.saturating_sub(CHUNK);
+        let mut chunk = vec![0; (start - from) as usize];
+        file.seek(SeekFrom::Start(from)).ok()?;
+        file.read_exact(&mut chunk).ok()?;
+        chunk.extend_from_slice(&tail);
+        tail = chunk;
+        start = from;
+    }
+}
+
+/// Removes every trace of the entries with `ids` from the journal, for when
+/// they are deleted for good. Operations left with no changes are dropped.
+/// Call this while holding the exclusive lock.
+pub fn forget(config: &Config, ids: &[String]) {
+    let operations = load(config);
+    let mentioned = operations
+        .iter()
+        .flat_map(|op| &op.changes)
+        .any(|c| c.clone().without(ids).as_ref() != Some(c));
+    if !mentioned {
+        return;
+    }
+    let operations: Vec<Operation> = operations
+        .into_iter()
+        .filter_map(|mut op| {
+            op.changes = op.changes.into_iter().filter_map(|c| c.without(ids)).collect();
+            (!op.changes.is_empty()).then_some(op)
+        })
+        .collect();
+    save(config, &operations);
+}
+
+pub fn undo(config: &Config) {
+    let _lock = storage::lock(config);
+    let mut operations = load(config);
+    let index = match operations.iter().rposition(|op| !op.undone) {
+        Some(index) => index,
+        None => {
+            println!("Nothing to undo.");
+            return;
+        }
+    };
+
+    let inverse: Vec<Change> = operations[index].changes.iter().rev().map(|c| c.inverse()).collect();
+    if let Err(e) = replay(storage::open(config).as_ref(), &inverse) {
+        eprintln!("Cannot undo '{}': {}.", operations[index].command, e);
+        return;
+    }
+
+    operations[index].undone = true;
+    save(config, &operations);
+    println!("Undid '{}' ({}).", operations[index].command, operations[index].describe());
+}
+
+pub fn redo(config: &Config) {
+    let _lock = storage::lock(config);
+    let mut operations = load(config);
+    let index = match operations.iter().position(|op| op.undone) {
+        Some(index) => index,
+        None => {
+            println!("Nothing to redo.");
+            return;
+        }
+    };
+
+    if let Err(e) = replay(storage::open(config).as_ref(), &operations[index].changes) {
+        eprintln!("Cannot redo '{}': {}.", operations[index].command, e);
+        return;
+    }
+
+    operations[index].undone = false;
+    save(config, &operations);
+    println!("Redid '{}' ({}).", operations[index].command, operations[index].describe());
+}
+
+/// Applies `changes` in order. If one no longer fits the log, those already
+/// applied are rolled back and the log is left as it was.
+fn replay(storage: &dyn Storage, changes: &[Change]) -> Result<(), String> {
+    for (index, change) in changes.iter().enumerate() {
+        if let Err(e) = change.check(storage) {
+            for applied in changes[..index].iter().rev() {
+                applied.inverse().apply(storage);
+            }
+            return Err(e);
+        }
+        change.apply(storage);
+    }
+    Ok(())
+}
+
+pub fn history(config: &Config, limit: usize) {
+    let operations = {
+        let _lock = storage::lock_shared(config);
+        load(config)
+    };
+
+    if operations.is_empty() {
+        println!("No history yet.");
+        return;
+    }
+
+    println!("Time\t\t\tCommand\t\tChanges");
+    println!("----\t\t\t-------\t\t-------");
+    for operation in operations.iter().rev().take(limit) {
+        println!(
+            "{}\t{}\t{}{}",
+            operation.time.format("%Y-%m-%d %H:%M:%S"),
+            operation.command,
+            operation.describe(),
+            if operation.undone { " (undone)" } else { "" }
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logger::{archive_logs, delete_logs, log_action, update_log};
+    use std::fs;
+
+    fn contents(config: &Config, store: Store) -> Vec<String> {
+        storage::open(config)
+            .read(store, &Filter::default())
+            .into_iter()
+            .map(|e| e.content)
+            .collect()
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let test_dir = std::env::temp_dir().join("acty_test_journal");
+        let _ = fs::remove_dir_all(&test_dir);
+        let config = Config {
+            log_file: test_dir.join("action_log.json").to_string_lossy().to_string(),
+            ..Config::default()
+        };
+
+        let old = Local::now() - chrono::Duration::days(10);
+        log_action(&config, "Old".to_string(), vec![], None, Some(old), None);
+        log_action(&config, "First".to_string(), vec![], None, None, None);
+        log_action(&config, "Second".to_string(), vec![], None, None, None);
+        delete_logs(&config, vec!["2".to_string(), "3".to_string()]);
+        assert_eq!(contents(&config, Store::Active), vec!["Old"]);
+
+        undo(&config);
+        assert_eq!(contents(&config, Store::Active), vec!["Old", "First", "Second"]);
+        redo(&config);
+        assert_eq!(contents(&config, Store::Active), vec!["Old"]);
+        undo(&config);
+
+        update_log(&config, "last", Some("Second (edited)".to_string()), None, None, None);
+        archive_logs(&config, 7);
+        assert_eq!(contents(&config, Store::Archive), vec!["Old"]);
+
+        // A whole archive run is undone at once
+        undo(&config);
+        assert!(contents(&config, Store::Archive).is_empty());
+        assert_eq!(contents(&config, Store::Active)[0], "Old");
+        undo(&config);
+        assert_eq!(contents(&config, Store::Active)[2], "Second");
+
+        // Recording a new command drops what could be redone
+        log_action(&config, "Third".to_string(), vec![], None, None, None);
+        redo(&config);
+        assert_eq!(contents(&config, Store::Active)[2], "Second");
+
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+
+    #[test]
+    fn test_record_appends() {
+        let test_dir = std::env::temp_dir().join("acty_test_journal_append");
+        let _ = fs::remove_dir_all(&test_dir);
+        let config = Config {
+            log_file: test_dir.join("action_log.json").to_string_lossy().to_string(),
+            ..Config::default()
+        };
+        let path = journal_path(&config);
+        let history = || fs::read_to_string(&path).unwrap();
+
+        log_action(&config, "First".to_string(), vec![], None, None, None);
+        // Longer than what is read from the end at once
+        log_action(&config, "x".repeat(100_000), vec![], None, None, None);
+        let before = history();
+        assert_eq!(last_line(&path).unwrap(), before.lines().last().unwrap());
+        log_action(&config, "Second".to_string(), vec![], None, None, None);
+        assert!(history().starts_with(&before));
+        let seqs: Vec<u64> = load(&config).iter().map(|op| op.seq).collect();
+        assert_eq!(seqs, vec![1, 2, 3]);
+
+        // Recording after an undo drops what was undone
+        undo(&config);
+        log_action(&config, "Third".to_string(), vec![], None, None, None);
+        let operations = load(&config);
+        assert_eq!(operations.len(), 3);
+        assert!(operations.iter().all(|op| !op.undone));
+
+        // Trimmed now and then, never below the limit
+        for _ in 0..2 * JOURNAL_LIMIT {
+            log_action(&config, "More".to_string(), vec![], None, None, None);
+        }
+        let count = load(&config).len();
+        assert!((JOURNAL_LIMIT..2 * JOURNAL_LIMIT).contains(&count), "{}", count);
+
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+
+    #[test]
+    fn test_undo_refuses_changed_entries() {
+        let test_dir = std::env::temp_dir().join("acty_test_journal_conflict");
+        let _ = fs::remove_dir_all(&test_dir);
+        let config = Config {
+            log_file: test_dir.join("action_log.json").to_string_lossy().to_string(),
+            ..Config::default()
+        };
+
+        log_action(&config, "Original".to_string(), vec![], None, None, None);
+        let storage = storage::open(&config);
+        let mut entry = storage.read(Store::Active, &Filter::default()).remove(0);
+        entry.content = "Edited by hand".to_string();
+        storage.update(Store::Active, &entry);
+
+        // Undoing the log would delete an entry that changed since
+        undo(&config);
+        assert_eq!(contents(&config, Store::Active), vec!["Edited by hand"]);
+        assert!(!load(&config)[0].undone);
+
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+}
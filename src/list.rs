@@ -1,61 +1,41 @@
 use crate::config::Config;
 use crate::log_entry::LogEntry;
-use crate::util::{should_include_log, sort_tags};
-use std::fs::File;
-use std::io::{BufRead, BufReader};
-
-pub fn list_logs(
-    config: &Config,
-    date: Option<String>,
-    range: Option<i64>,
-    tags: Vec<String>,
-    search: Option<String>,
-    use_archive: bool,
-) {
-    let path = if use_archive {
-        std::path::Path::new(&config.log_file).with_file_name("archive.json")
-    } else {
-        std::path::PathBuf::from(&config.log_file)
-    };
+use crate::logger::{resolve_id, split_selector};
+use crate::output::{self, paint, EntryRecord, OutputFormat, ID_COLOR, TAG_COLOR};
+use crate::storage::{self, Scope, Store};
+use crate::util::{entry_durations, format_duration, sort_tags, Filter};
+use chrono::Local;
 
-    let file = match File::open(&path) {
-        Ok(f) => f,
-        Err(_) => {
-            println!("No logs found.");
-            return;
-        }
+pub fn list_logs(config: &Config, filter: Filter, scope: Scope, format: OutputFormat) {
+    let entries = {
+        let _lock = storage::lock_shared(config);
+        storage::read_scope_timed(storage::open(config).as_ref(), scope, &filter, Local::now())
     };
-    let reader = BufReader::new(file);
 
-    println!("ID\tTime\t\tGap\t\tTags\t\tContent");
-    println!("--\t----\t\t---\t\t----\t\t-------");
+    if format != OutputFormat::Table {
+        let records = output::entry_records(&entries);
+        output::print_json(format, "entries", "entry", &records);
+        return;
+    }
 
-    let mut previous_time: Option<chrono::DateTime<chrono::Local>> = None;
-    let mut total_duration_seconds: i64 = 0;
+    if entries.is_empty() {
+        println!("No logs found.");
+        return;
+    }
 
-    for (index, line) in reader.lines().enumerate() {
-        let log_entry: LogEntry =
-            serde_json::from_str(&line.expect("Unable to read the log entry"))
-                .expect("Unable to deserialize the log entry");
+    println!("ID\tTime\t\tDuration\tTags\t\tContent");
+    println!("--\t----\t\t--------\t----\t\t-------");
 
-        if !should_include_log(&log_entry, &date, &range, &tags, &search) {
-            continue;
-        }
+    let mut total_duration_seconds: i64 = 0;
 
-        let gap_str = match previous_time {
-            Some(prev) => {
-                let duration = log_entry.timestamp - prev;
-                let seconds = duration.num_seconds();
+    for (store, log_entry, duration) in &entries {
+        let duration_str = match *duration {
+            Some(seconds) => {
                 total_duration_seconds += seconds;
-                
-                if seconds < 60 {
-                    format!("{}s", seconds)
-                } else if seconds < 3600 {
-                    format!("{}m", seconds / 60)
+                if log_entry.running {
+                    format!("{} (running)", format_duration(seconds))
                 } else {
-                    let hours = seconds / 3600;
-                    let minutes = (seconds % 3600) / 60;
-                    format!("{}h {}m", hours, minutes)
+                    format_duration(seconds)
                 }
             }
             None => "-".to_string(),
@@ -65,14 +45,12 @@ pub fn list_logs(
 
         println!(
             "{}\t{}\t{}\t{}\t{}",
-            index + 1,
-            log_entry.timestamp.format("%Y-%m-%d %H:%M"),
-            gap_str,
-            sorted_tags.join(", "),
-            log_entry.content,
+            paint(config, ID_COLOR, &store.display_id(&log_entry.id)),
+            log_entry.timestamp.format(&config.time_format),
+            duration_str,
+            paint(config, TAG_COLOR, &sorted_tags.join(", ")),
+            log_entry.summary(),
         );
-
-        previous_time = Some(log_entry.timestamp);
     }
 
     if total_duration_seconds > 0 {
@@ -82,3 +60,104 @@ pub fn list_logs(
         println!("\nTotal Duration: {}h {}m {}s", hours, minutes, seconds);
     }
 }
+
+/// Prints one entry in full, with its notes.
+pub fn show_log(config: &Config, id: &str, format: OutputFormat) {
+    let (store, selector) = split_selector(id);
+    let entries = {
+        let _lock = storage::lock_shared(config);
+        storage::open(config).read(store, &Filter::default())
+    };
+    let ids: Vec<String> = entries.iter().map(|e| e.id.clone()).collect();
+    let index = match resolve_id(&ids, selector) {
+        Ok(index) => index,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    // The duration may be the gap since the previous entry
+    let duration = entry_durations(&entries, Local::now())[index];
+    let entry = &entries[index];
+
+    if format != OutputFormat::Table {
+        let record = EntryRecord::new(store, entry, duration);
+        output::print_json(format, "entries", "entry", &[record]);
+        return;
+    }
+
+    print!("{}", render_entry(config, store, entry, duration));
+}
+
+/// One entry's fields, one per line, with its notes indented below them.
+fn render_entry(config: &Config, store: Store, entry: &LogEntry, duration: Option<i64>) -> String {
+    let duration_str = match duration {
+        Some(seconds) if entry.running => format!("{} (running)", format_duration(seconds)),
+        Some(seconds) => format_duration(seconds),
+        None => "-".to_string(),
+    };
+    let mut text = format!(
+        "ID:       {}\nTime:     {}\nDuration: {}\nTags:     {}\nContent:  {}\n",
+        paint(config, ID_COLOR, &store.display_id(&entry.id)),
+        entry.timestamp.format(&config.time_format),
+        duration_str,
+        paint(config, TAG_COLOR, &sort_tags(entry.tags.clone()).join(", ")),
+        entry.content
+    );
+    if let Some(notes) = &entry.notes {
+        text.push('\n');
+        for line in notes.lines() {
+            text.push_str(&format!("    {}\n", line));
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ColorMode;
+    use crate::logger::{archive_logs, log_action};
+    use std::fs;
+
+    #[test]
+    fn test_show_log() {
+        let test_dir = std::env::temp_dir().join("acty_test_show");
+        let _ = fs::remove_dir_all(&test_dir);
+        let config = Config {
+            log_file: test_dir.join("action_log.json").to_string_lossy().to_string(),
+            color: ColorMode::Never,
+            ..Config::default()
+        };
+
+        let old = Local::now() - chrono::Duration::days(10);
+        log_action(
+            &config,
+            "Coffee".to_string(),
+            vec!["cafe".to_string(), "break".to_string()],
+            Some(900),
+            Some(old),
+            Some("with Sam\n\n- roadmap".to_string()),
+        );
+        archive_logs(&config, 7);
+        let entry = &storage::open(&config).read(Store::Archive, &Filter::default())[0];
+
+        assert_eq!(
+            render_entry(&config, Store::Archive, entry, Some(900)),
+            format!(
+                "ID:       a:{}\nTime:     {}\nDuration: 15m\nTags:     cafe, break\nContent:  Coffee\n\n    with Sam\n    \n    - roadmap\n",
+                entry.id,
+                old.format(&config.time_format)
+            )
+        );
+
+        let mut running = entry.clone();
+        running.running = true;
+        running.notes = None;
+        let text = render_entry(&config, Store::Active, &running, None);
+        assert!(text.contains("Duration: -\n"));
+        assert!(text.ends_with("Content:  Coffee\n"));
+
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+}
@@ -1,15 +1,148 @@
-use chrono::{DateTime, Local};
+use chrono::{DateTime, Local, Utc};
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hash, Hasher};
 
-#[derive(Serialize, Deserialize, Debug)]
+// IDs always start with a letter so they can never be mistaken for the line
+// numbers older versions accepted in their place.
+const ID_FIRST_CHARS: &[u8] = b"abcdefghjkmnpqrstvwxyz";
+const ID_CHARS: &[u8] = b"0123456789abcdefghjkmnpqrstvwxyz";
+pub const ID_LEN: usize = 8;
+
+/// The version of the entry format this build writes. Bump it together with
+/// a step in [`LogEntry::upgrade`] when a field changes meaning; fields that
+/// are merely added need neither, as long as they are optional.
+pub const ENTRY_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct LogEntry {
+    #[serde(default)]
+    pub id: String,
     #[serde(with = "local_date_time")]
     pub timestamp: DateTime<Local>,
     pub content: String,
+    #[serde(default)]
     pub tags: Vec<String>,
+    /// A longer, possibly multi-line body; `content` stays one line.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    /// How long the activity took, in seconds, when it is known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration: Option<i64>,
+    /// Set while the timer started by `acty start` is running.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub running: bool,
+    /// When the entry was moved to the trash.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "optional_local_date_time"
+    )]
+    pub deleted_at: Option<DateTime<Local>>,
+    /// The format version the entry was written in; 0 for entries written
+    /// before versions were recorded.
+    #[serde(default, skip_serializing_if = "is_unversioned")]
+    pub schema_version: u32,
+    /// Fields added by a newer acty, kept so rewriting the entry doesn't
+    /// lose them.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+fn is_unversioned(version: &u32) -> bool {
+    *version == 0
 }
 
-mod local_date_time {
+impl LogEntry {
+    pub fn new(timestamp: DateTime<Local>, content: String, tags: Vec<String>) -> Self {
+        LogEntry {
+            id: random_id(&timestamp, &content),
+            timestamp,
+            content,
+            tags,
+            notes: None,
+            duration: None,
+            running: false,
+            deleted_at: None,
+            schema_version: ENTRY_SCHEMA_VERSION,
+            extra: Map::new(),
+        }
+    }
+
+    /// The content with a hint at the notes, for one-line views such as
+    /// `list`.
+    pub fn summary(&self) -> String {
+        match self.notes.as_deref().map(|notes| notes.lines().count()) {
+            Some(lines) if lines > 0 => format!("{} (+{} line(s) of notes)", self.content, lines),
+            _ => self.content.clone(),
+        }
+    }
+
+    /// Assigns an ID to an entry written before IDs existed. The ID is derived
+    /// from the entry itself with a fixed hash, so it is the same every time
+    /// the entry is read, whichever build of acty reads it and in whatever
+    /// time zone.
+    pub fn assign_legacy_id(&mut self, salt: usize) {
+        let timestamp = self.timestamp.with_timezone(&Utc).to_rfc3339();
+        let key = format!("{}\n{}\n{}", timestamp, self.content, salt);
+        self.id = encode_id(fnv1a(key.as_bytes()));
+    }
+
+    /// Brings an entry written by an older acty up to
+    /// [`ENTRY_SCHEMA_VERSION`]. Returns false if it already was.
+    pub fn upgrade(&mut self) -> bool {
+        if self.schema_version >= ENTRY_SCHEMA_VERSION {
+            return false;
+        }
+        // 0 -> 1: entries gained IDs, which are assigned whenever an entry
+        // without one is read, so only the marker is missing.
+        self.schema_version = ENTRY_SCHEMA_VERSION;
+        true
+    }
+
+    /// Gives the entry a new ID, e.g. when it clashes with another entry's.
+    pub fn reassign_id(&mut self) {
+        self.id = random_id(&self.timestamp, &self.content);
+    }
+}
+
+fn random_id(timestamp: &DateTime<Local>, content: &str) -> String {
+    let mut hasher = RandomState::new().build_hasher();
+    timestamp.to_rfc3339().hash(&mut hasher);
+    content.hash(&mut hasher);
+    encode_id(hasher.finish())
+}
+
+/// 64-bit FNV-1a, which unlike the standard library's hashers is specified
+/// and never changes.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Whether `id` has the shape of an ID acty makes, so it can't be mistaken
+/// for `last`, an `a:` selector or anything else when looked up.
+pub fn is_valid_id(id: &str) -> bool {
+    let bytes = id.as_bytes();
+    bytes.len() == ID_LEN
+        && ID_FIRST_CHARS.contains(&bytes[0])
+        && bytes[1..].iter().all(|b| ID_CHARS.contains(b))
+}
+
+fn encode_id(mut hash: u64) -> String {
+    let mut id = String::with_capacity(ID_LEN);
+    id.push(ID_FIRST_CHARS[(hash % ID_FIRST_CHARS.len() as u64) as usize] as char);
+    hash /= ID_FIRST_CHARS.len() as u64;
+    for _ in 1..ID_LEN {
+        id.push(ID_CHARS[(hash % ID_CHARS.len() as u64) as usize] as char);
+        hash /= ID_CHARS.len() as u64;
+    }
+    id
+}
+
+pub mod local_date_time {
     use chrono::{DateTime, Local};
     use serde::{self, Deserialize, Deserializer, Serializer};
 
@@ -32,24 +165,109 @@ mod local_date_time {
     }
 }
 
+mod optional_local_date_time {
+    use chrono::{DateTime, Local};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(datetime: &Option<DateTime<Local>>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match datetime {
+            Some(datetime) => super::local_date_time::serialize(datetime, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<DateTime<Local>>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Option::<String>::deserialize(deserializer)?
+            .map(|s| {
+                DateTime::parse_from_rfc3339(&s)
+                    .map(|datetime| datetime.with_timezone(&Local))
+                    .map_err(serde::de::Error::custom)
+            })
+            .transpose()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_summary() {
+        let mut entry = LogEntry::new(Local::now(), "Standup".to_string(), vec![]);
+        assert_eq!(entry.summary(), "Standup");
+        entry.notes = Some("- roadmap\n- hiring".to_string());
+        assert_eq!(entry.summary(), "Standup (+2 line(s) of notes)");
+        entry.notes = Some("one line".to_string());
+        assert_eq!(entry.summary(), "Standup (+1 line(s) of notes)");
+        entry.notes = Some(String::new());
+        assert_eq!(entry.summary(), "Standup");
+    }
+
     #[test]
     fn test_serialize_deserialize() {
         let now = Local::now();
-        let log_entry = LogEntry {
-            timestamp: now,
-            content: "Test content".to_string(),
-            tags: vec!["tag1".to_string(), "tag2".to_string()],
-        };
+        let log_entry = LogEntry::new(
+            now,
+            "Test content".to_string(),
+            vec!["tag1".to_string(), "tag2".to_string()],
+        );
 
         let serialized = serde_json::to_string(&log_entry).unwrap();
         let deserialized: LogEntry = serde_json::from_str(&serialized).unwrap();
 
+        assert_eq!(log_entry.id, deserialized.id);
         assert_eq!(log_entry.content, deserialized.content);
         assert_eq!(log_entry.tags, deserialized.tags);
         assert_eq!(log_entry.timestamp, deserialized.timestamp);
     }
+
+    #[test]
+    fn test_legacy_entry_gets_stable_id() {
+        let line = r#"{"timestamp":"2024-01-01T09:00:00+00:00","content":"Old","tags":[]}"#;
+        let mut first: LogEntry = serde_json::from_str(line).unwrap();
+        let mut second: LogEntry = serde_json::from_str(line).unwrap();
+        assert!(first.id.is_empty());
+
+        first.assign_legacy_id(0);
+        second.assign_legacy_id(0);
+        assert_eq!(first.id, second.id);
+        assert_eq!(first.id.len(), ID_LEN);
+        assert!(first.id.chars().next().unwrap().is_ascii_alphabetic());
+
+        // The hash is fixed, so the ID never changes between builds
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(first.id, "eypmmzw6");
+        assert!(is_valid_id(&first.id));
+        for id in ["last", "a:eypmmzw6", "eypm mzw6", "eypm,zw6", "1ypmmzw6", "eypmmzw6x", "eypmmzwi"] {
+            assert!(!is_valid_id(id), "{}", id);
+        }
+    }
+
+    #[test]
+    fn test_unknown_fields_are_kept() {
+        let line = r#"{"id":"k3x9ab01","timestamp":"2024-01-01T09:00:00+00:00","content":"Old","mood":"happy","schema_version":7}"#;
+        let mut entry: LogEntry = serde_json::from_str(line).unwrap();
+        assert!(entry.tags.is_empty());
+        assert_eq!(entry.extra["mood"], "happy");
+        assert!(!entry.upgrade());
+
+        let serialized = serde_json::to_string(&entry).unwrap();
+        assert!(serialized.contains(r#""mood":"happy""#));
+        assert!(serialized.contains(r#""schema_version":7"#));
+
+        let mut legacy: LogEntry = serde_json::from_str(
+            r#"{"timestamp":"2024-01-01T09:00:00+00:00","content":"Old","tags":[]}"#,
+        )
+        .unwrap();
+        assert_eq!(legacy.schema_version, 0);
+        assert!(!serde_json::to_string(&legacy).unwrap().contains("schema_version"));
+        assert!(legacy.upgrade());
+        assert_eq!(legacy.schema_version, ENTRY_SCHEMA_VERSION);
+    }
 }
@@ -1,228 +1,492 @@
-use crate::config::Config;
+use crate::config::{Backend, Config};
+use crate::journal::{self, Change};
 use crate::log_entry::LogEntry;
-use chrono::Local;
-use std::collections::HashSet;
-use std::fs::OpenOptions;
-use std::io::Write;
-
-pub fn log_action(config: &Config, content: String, tags: Vec<String>) {
-    let unique_tags: Vec<String> = tags
-        .into_iter()
-        .collect::<HashSet<_>>()
-        .into_iter()
-        .collect();
+use crate::output::{self, EntryRecord, OutputFormat};
+use crate::storage::{self, JsonLinesStorage, Storage, Store, ARCHIVE_ID_PREFIX};
+use crate::trash;
+use crate::util::{format_duration, sort_tags, Filter};
+use chrono::{DateTime, Local};
+
+pub fn log_action(
+    config: &Config,
+    content: String,
+    tags: Vec<String>,
+    duration: Option<i64>,
+    at: Option<DateTime<Local>>,
+    notes: Option<String>,
+) {
+    // In the order given, so `report --split first` knows the first tag
+    let unique_tags = config.resolve_tags(
+        tags.into_iter()
+            .chain(config.default_tags.iter().cloned())
+            .collect(),
+    );
+
+    let mut log_entry = LogEntry::new(at.unwrap_or_else(Local::now), content, unique_tags);
+    log_entry.duration = duration;
+    log_entry.notes = notes.filter(|n| !n.trim().is_empty());
+    let _lock = storage::lock(config);
+    add_entry(storage::open(config).as_ref(), &log_entry, at.is_some());
+    journal::record(
+        config,
+        "log",
+        vec![Change::Add {
+            store: Store::Active,
+            entries: vec![log_entry.clone()],
+        }],
+    );
+
+    println!("Log entry {} added successfully!", log_entry.id);
+}
 
-    let log_entry = LogEntry {
-        timestamp: Local::now(),
-        content,
-        tags: unique_tags,
-    };
+/// Stores a new active entry; backdated entries are inserted in
+/// chronological order so gaps between entries stay correct.
+fn add_entry(storage: &dyn Storage, entry: &LogEntry, backdated: bool) {
+    if backdated {
+        storage.insert(Store::Active, std::slice::from_ref(entry));
+    } else {
+        storage.append(Store::Active, std::slice::from_ref(entry));
+    }
+}
 
-    let path = std::path::Path::new(&config.log_file);
-    if let Some(parent) = path.parent() {
-        std::fs::create_dir_all(parent).expect("Unable to create log directory");
+/// Resolves a user supplied ID to a zero-based index into `ids`.
+///
+/// Accepts `last`, the positional form (1-based line number, as printed by
+/// older versions of `list`) and stable IDs or any unique prefix of one.
+pub fn resolve_id(ids: &[String], selector: &str) -> Result<usize, String> {
+    if selector == "last" {
+        return if ids.is_empty() {
+            Err("The log is empty.".to_string())
+        } else {
+            Ok(ids.len() - 1)
+        };
     }
 
-    let mut file = OpenOptions::new()
-        .append(true)
-        .create(true)
-        .open(config.log_file.to_string())
-        .expect("Unable to open or create the log file");
+    if !selector.is_empty() && selector.chars().all(|c| c.is_ascii_digit()) {
+        return match selector.parse::<usize>() {
+            Ok(n) if n >= 1 && n <= ids.len() => Ok(n - 1),
+            _ => Err(format!(
+                "Invalid ID: {}. Use 'list' command to see available IDs.",
+                selector
+            )),
+        };
+    }
 
-    let serialized = serde_json::to_string(&log_entry).expect("Unable to serialize the log entry");
-    writeln!(file, "{}", serialized).expect("Unable to write to the log file");
+    let selector = selector.to_lowercase();
+    let matches: Vec<usize> = ids
+        .iter()
+        .enumerate()
+        .filter(|(_, id)| id.starts_with(&selector))
+        .map(|(index, _)| index)
+        .collect();
 
-    println!("Log entry added successfully!");
+    match matches.len() {
+        0 => Err(format!(
+            "Invalid ID: {}. Use 'list' command to see available IDs.",
+            selector
+        )),
+        1 => Ok(matches[0]),
+        _ => Err(format!(
+            "ID prefix '{}' is ambiguous: matches {}",
+            selector,
+            matches
+                .iter()
+                .map(|&i| ids[i].as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
 }
 
-pub fn delete_logs(config: &Config, ids: Vec<usize>) {
-    let path = std::path::Path::new(&config.log_file);
-    if !path.exists() {
-        eprintln!("Log file not found.");
-        return;
+/// Splits the store off a selector: `a:` picks the archive (as in the IDs
+/// shown by `list --all`), anything else the active log.
+pub fn split_selector(selector: &str) -> (Store, &str) {
+    match selector.get(..ARCHIVE_ID_PREFIX.len()) {
+        Some(prefix) if prefix.eq_ignore_ascii_case(ARCHIVE_ID_PREFIX) => {
+            (Store::Archive, &selector[ARCHIVE_ID_PREFIX.len()..])
+        }
+        _ => (Store::Active, selector),
     }
+}
 
-    let contents = std::fs::read_to_string(path).expect("Unable to read log file");
-    let mut lines: Vec<&str> = contents.lines().collect();
+/// Looks up a single entry by any form accepted by [`resolve_id`], optionally
+/// prefixed with `a:` for the archive.
+pub fn find_entry(storage: &dyn Storage, selector: &str) -> Result<(Store, LogEntry), String> {
+    let (store, selector) = split_selector(selector);
+    let mut entries = storage.read(store, &Filter::default());
+    let ids: Vec<String> = entries.iter().map(|e| e.id.clone()).collect();
+    let index = resolve_id(&ids, selector)?;
+    Ok((store, entries.swap_remove(index)))
+}
 
-    // Sort IDs in descending order to prevent shifting indices from affecting subsequent deletions
-    let mut sorted_ids = ids;
-    sorted_ids.sort_by(|a, b| b.cmp(a));
-    sorted_ids.dedup(); // Remove duplicates
+/// Resolves the selectors that refer to entries of `store` to their IDs,
+/// reporting the ones that match nothing.
+pub fn select_ids(storage: &dyn Storage, store: Store, selectors: &[String]) -> Vec<String> {
+    let entry_ids: Vec<String> = storage
+        .read(store, &Filter::default())
+        .into_iter()
+        .map(|e| e.id)
+        .collect();
 
-    let mut deleted_count = 0;
-    for index in sorted_ids {
-        if index == 0 || index > lines.len() {
-            eprintln!("Skipping invalid ID: {}", index);
+    let mut selected: Vec<String> = Vec::new();
+    for id in selectors {
+        let (selector_store, selector) = split_selector(id);
+        if selector_store != store {
             continue;
         }
-        lines.remove(index - 1);
-        deleted_count += 1;
+        match resolve_id(&entry_ids, selector) {
+            Ok(index) => {
+                if !selected.contains(&entry_ids[index]) {
+                    selected.push(entry_ids[index].clone());
+                }
+            }
+            Err(e) => eprintln!("Skipping {}", e),
+        }
     }
+    selected
+}
 
-    let mut file = std::fs::File::create(path).expect("Unable to open log file for writing");
-    for line in lines {
-        writeln!(file, "{}", line).expect("Unable to write to log file");
-    }
+pub fn delete_logs(config: &Config, ids: Vec<String>) {
+    let deleted = trash_entries(config, &ids);
+    println!("{} log entry(ies) moved to the trash.", deleted);
+}
 
-    println!("{} log entry(ies) deleted successfully.", deleted_count);
+/// Moves the selected entries to the trash, returning how many there were.
+pub fn trash_entries(config: &Config, ids: &[String]) -> usize {
+    let _lock = storage::lock(config);
+    let storage = storage::open(config);
+
+    let mut deleted = Vec::new();
+    let mut changes = Vec::new();
+    for store in [Store::Active, Store::Archive] {
+        let selected = select_ids(storage.as_ref(), store, ids);
+        if !selected.is_empty() {
+            let (trashed, trash_changes) = trash::move_to_trash(storage.as_ref(), store, &selected);
+            deleted.extend(trashed);
+            changes.extend(trash_changes);
+        }
+    }
+    journal::record(config, &format!("delete {}", ids.join(" ")), changes);
+    deleted.len()
 }
 
-pub fn get_log_count(config: &Config) -> usize {
-    let path = std::path::Path::new(&config.log_file);
-    if !path.exists() {
-        return 0;
+pub fn update_log(
+    config: &Config,
+    id: &str,
+    new_content: Option<String>,
+    new_tags: Option<Vec<String>>,
+    at: Option<DateTime<Local>>,
+    new_notes: Option<String>,
+) {
+    match edit_entry(config, id, new_content, new_tags, at, new_notes) {
+        Ok((store, log_entry)) => {
+            println!("Log entry {} updated successfully.", store.display_id(&log_entry.id))
+        }
+        Err(e) => eprintln!("{}", e),
     }
-    let contents = std::fs::read_to_string(path).unwrap_or_default();
-    contents.lines().count()
 }
 
-pub fn update_log(config: &Config, index: usize, new_content: String, new_tags: Option<Vec<String>>) {
-    let path = std::path::Path::new(&config.log_file);
-    if !path.exists() {
-        eprintln!("Log file not found.");
-        return;
+/// Changes the given parts of one entry, returning it as stored. Empty
+/// `new_notes` remove the notes.
+pub fn edit_entry(
+    config: &Config,
+    id: &str,
+    new_content: Option<String>,
+    new_tags: Option<Vec<String>>,
+    at: Option<DateTime<Local>>,
+    new_notes: Option<String>,
+) -> Result<(Store, LogEntry), String> {
+    let _lock = storage::lock(config);
+    let storage = storage::open(config);
+    let (store, mut log_entry) = find_entry(storage.as_ref(), id)?;
+    let before = log_entry.clone();
+
+    if let Some(content) = new_content {
+        log_entry.content = content;
     }
 
-    let contents = std::fs::read_to_string(path).expect("Unable to read log file");
-    let lines: Vec<&str> = contents.lines().collect();
+    if let Some(timestamp) = at {
+        log_entry.timestamp = timestamp;
+    }
 
-    if index == 0 || index > lines.len() {
-        eprintln!("Invalid ID: {}. Use 'list' command to see available IDs.", index);
-        return;
+    if let Some(notes) = new_notes {
+        log_entry.notes = Some(notes).filter(|n| !n.trim().is_empty());
     }
 
-    let mut log_entry: LogEntry = serde_json::from_str(lines[index - 1]).expect("Unable to deserialize log entry");
-    
-    log_entry.content = new_content;
-    
     if let Some(tags) = new_tags {
-        let unique_tags: Vec<String> = tags
-            .into_iter()
-            .collect::<HashSet<_>>()
-            .into_iter()
-            .collect();
-        log_entry.tags = unique_tags;
+        log_entry.tags = config.resolve_tags(tags);
     }
 
-    let serialized = serde_json::to_string(&log_entry).expect("Unable to serialize updated log entry");
-    // We need to own the strings to put them back into the vector if we want to write them all at once
-    // Alternatively, we can just write the file directly from the iteration logic, but let's stick to the vector approach for simplicity in replacing.
-    // However, lines is Vec<&str>, so we can't replace a &str with a String easily without reconstructing the whole buffer or using a different approach.
-    // Let's read, modify the specific entry in a list of Strings, and write back.
-    
-    let mut lines_owned: Vec<String> = contents.lines().map(|s| s.to_string()).collect();
-    lines_owned[index - 1] = serialized;
+    storage.update(store, &log_entry);
+    journal::record(
+        config,
+        &format!("edit {}", id),
+        vec![Change::Update {
+            store,
+            before: Box::new(before),
+            after: Box::new(log_entry.clone()),
+        }],
+    );
+    Ok((store, log_entry))
+}
 
-    let mut file = std::fs::File::create(path).expect("Unable to open log file for writing");
-    for line in lines_owned {
-        writeln!(file, "{}", line).expect("Unable to write to log file");
+pub fn copy_log(
+    config: &Config,
+    id: &str,
+    new_content: Option<String>,
+    at: Option<DateTime<Local>>,
+) {
+    match copy_entry(config, id, new_content, at) {
+        Ok((store, original_id, log_entry)) => println!(
+            "Log entry {} copied to new entry {} successfully!",
+            store.display_id(&original_id),
+            log_entry.id
+        ),
+        Err(e) => eprintln!("{}", e),
     }
+}
 
-    println!("Log entry {} updated successfully.", index);
+/// Logs a copy of one entry, returning where the original is, its ID and
+/// the new entry.
+pub fn copy_entry(
+    config: &Config,
+    id: &str,
+    new_content: Option<String>,
+    at: Option<DateTime<Local>>,
+) -> Result<(Store, String, LogEntry), String> {
+    let _lock = storage::lock(config);
+    let storage = storage::open(config);
+    let (store, original_entry) = find_entry(storage.as_ref(), id)?;
+
+    // The notes go with the content they describe
+    let (content, notes) = match new_content {
+        Some(content) => (content, None),
+        None => (original_entry.content, original_entry.notes),
+    };
+    let mut log_entry = LogEntry::new(at.unwrap_or_else(Local::now), content, original_entry.tags);
+    log_entry.notes = notes;
+    add_entry(storage.as_ref(), &log_entry, at.is_some());
+    journal::record(
+        config,
+        &format!("copy {}", id),
+        vec![Change::Add {
+            store: Store::Active,
+            entries: vec![log_entry.clone()],
+        }],
+    );
+    Ok((store, original_entry.id, log_entry))
 }
 
-pub fn copy_log(config: &Config, index: usize, new_content: Option<String>) {
-    let path = std::path::Path::new(&config.log_file);
-    if !path.exists() {
-        eprintln!("Log file not found.");
+pub fn archive_logs(config: &Config, days: i64) {
+    let cutoff_date = Local::now().date_naive() - chrono::Duration::days(days);
+    let _lock = storage::lock(config);
+    let archived = storage::open(config).archive(cutoff_date);
+
+    if archived.is_empty() {
+        println!("No logs found older than {} days.", days);
         return;
     }
+    journal::record(
+        config,
+        &format!("archive {}", days),
+        vec![Change::Move {
+            from: Store::Active,
+            to: Store::Archive,
+            entries: archived.clone(),
+        }],
+    );
+
+    println!("Archived {} logs older than {} days.", archived.len(), days);
+}
 
-    let contents = std::fs::read_to_string(path).expect("Unable to read log file");
-    let lines: Vec<&str> = contents.lines().collect();
-
-    if index == 0 || index > lines.len() {
-        eprintln!("Invalid ID: {}. Use 'list' command to see available IDs.", index);
+/// Splits the single `archive.json` into monthly partitions.
+pub fn migrate_archive(config: &Config) {
+    if config.backend != Backend::Json {
+        eprintln!("The sqlite backend keeps archived entries in its database; there is nothing to migrate.");
         return;
     }
 
-    let original_entry: LogEntry = serde_json::from_str(lines[index - 1]).expect("Unable to deserialize log entry");
-    
-    let log_entry = LogEntry {
-        timestamp: Local::now(),
-        content: new_content.unwrap_or(original_entry.content),
-        tags: original_entry.tags,
-    };
-
-    let mut file = OpenOptions::new()
-        .append(true)
-        .create(true)
-        .open(config.log_file.to_string())
-        .expect("Unable to open or create the log file");
+    let _lock = storage::lock(config);
+    match JsonLinesStorage::new(config).split_archive() {
+        Ok(0) => println!("No entries in archive.json to migrate."),
+        Ok(count) => println!("Moved {} archived entries into monthly files.", count),
+        Err(e) => eprintln!("Cannot migrate the archive: {}.", e),
+    }
+}
 
-    let serialized = serde_json::to_string(&log_entry).expect("Unable to serialize the log entry");
-    writeln!(file, "{}", serialized).expect("Unable to write to the log file");
+/// Moves archived entries matching `filter` back into the log at their
+/// chronological position.
+pub fn unarchive_logs(config: &Config, filter: Filter, dry_run: bool) {
+    let _lock = storage::lock(config);
+    let storage = storage::open(config);
+    let matching = storage.read(Store::Archive, &filter);
 
-    println!("Log entry {} copied to new entry successfully!", index);
-}
+    if matching.is_empty() {
+        println!("No archived logs match.");
+        return;
+    }
 
-pub fn archive_logs(config: &Config, days: i64) {
-    let path = std::path::Path::new(&config.log_file);
-    if !path.exists() {
-        println!("No logs to archive.");
+    if dry_run {
+        for entry in &matching {
+            println!(
+                "{}\t{}\t{}\t{}",
+                entry.id,
+                entry.timestamp.format(&config.time_format),
+                sort_tags(entry.tags.clone()).join(", "),
+                entry.content
+            );
+        }
+        println!("Dry run: {} log entry(ies) would be restored from the archive.", matching.len());
         return;
     }
 
-    let contents = std::fs::read_to_string(path).expect("Unable to read log file");
-    let mut keep_logs: Vec<String> = Vec::new();
-    let mut archive_logs: Vec<String> = Vec::new();
+    let ids: Vec<String> = matching.into_iter().map(|e| e.id).collect();
+    let restored = storage.move_entries(Store::Archive, Store::Active, &ids);
+    journal::record(
+        config,
+        "unarchive",
+        vec![Change::Move {
+            from: Store::Archive,
+            to: Store::Active,
+            entries: restored.clone(),
+        }],
+    );
+
+    println!("Restored {} log entry(ies) from the archive.", restored.len());
+}
 
-    let cutoff_date = Local::now().date_naive() - chrono::Duration::days(days);
+/// Stops every running timer, recording how long each ran. Returns the
+/// changes for the journal.
+fn stop_running(storage: &dyn Storage) -> Vec<Change> {
+    let now = Local::now();
+    let mut stopped = Vec::new();
+    for entry in storage.read(Store::Active, &Filter::default()) {
+        if !entry.running {
+            continue;
+        }
+        let mut after = entry.clone();
+        after.running = false;
+        after.duration = Some((now - entry.timestamp).num_seconds().max(0));
+        storage.update(Store::Active, &after);
+        stopped.push(Change::Update {
+            store: Store::Active,
+            before: Box::new(entry),
+            after: Box::new(after),
+        });
+    }
+    stopped
+}
 
-    for line in contents.lines() {
-        if let Ok(entry) = serde_json::from_str::<LogEntry>(line) {
-            if entry.timestamp.date_naive() < cutoff_date {
-                archive_logs.push(line.to_string());
-            } else {
-                keep_logs.push(line.to_string());
-            }
+fn print_stopped(changes: &[Change]) {
+    for change in changes {
+        if let Change::Update { after, .. } = change {
+            println!(
+                "Stopped '{}' after {}.",
+                after.content,
+                format_duration(after.duration.unwrap_or(0))
+            );
         }
     }
+}
 
-    if archive_logs.is_empty() {
-        println!("No logs found older than {} days.", days);
+pub fn start_timer(config: &Config, content: String, tags: Vec<String>) {
+    // In the order given, so `report --split first` knows the first tag
+    let unique_tags = config.resolve_tags(
+        tags.into_iter()
+            .chain(config.default_tags.iter().cloned())
+            .collect(),
+    );
+
+    let _lock = storage::lock(config);
+    let storage = storage::open(config);
+
+    let mut changes = stop_running(storage.as_ref());
+    print_stopped(&changes);
+
+    let mut log_entry = LogEntry::new(Local::now(), content, unique_tags);
+    log_entry.running = true;
+    storage.append(Store::Active, std::slice::from_ref(&log_entry));
+    changes.push(Change::Add {
+        store: Store::Active,
+        entries: vec![log_entry.clone()],
+    });
+    journal::record(config, "start", changes);
+
+    println!("Started '{}' ({}).", log_entry.content, log_entry.id);
+}
+
+pub fn stop_timer(config: &Config) {
+    let _lock = storage::lock(config);
+    let stopped = stop_running(storage::open(config).as_ref());
+
+    if stopped.is_empty() {
+        println!("No timer running.");
         return;
     }
+    print_stopped(&stopped);
+    journal::record(config, "stop", stopped);
+}
 
-    // Append to archive file
-    let archive_path = path.with_file_name("archive.json");
-    let mut archive_file = OpenOptions::new()
-        .append(true)
-        .create(true)
-        .open(archive_path)
-        .expect("Unable to open archive file");
+pub fn timer_status(config: &Config, format: OutputFormat) {
+    let entries = {
+        let _lock = storage::lock_shared(config);
+        storage::open(config).read(Store::Active, &Filter::default())
+    };
 
-    for line in &archive_logs {
-        writeln!(archive_file, "{}", line).expect("Unable to write to archive file");
+    let now = Local::now();
+    let running: Vec<&LogEntry> = entries.iter().filter(|e| e.running).collect();
+    if format != OutputFormat::Table {
+        let records: Vec<EntryRecord> = running
+            .iter()
+            .map(|e| EntryRecord::new(Store::Active, e, Some((now - e.timestamp).num_seconds().max(0))))
+            .collect();
+        output::print_json(format, "timers", "timer", &records);
+        return;
     }
 
-    // Overwrite main log file
-    let mut main_file = std::fs::File::create(path).expect("Unable to open log file for writing");
-    for line in &keep_logs {
-        writeln!(main_file, "{}", line).expect("Unable to write to log file");
+    if running.is_empty() {
+        println!("No timer running.");
+        return;
     }
 
-    println!("Archived {} logs older than {} days.", archive_logs.len(), days);
+    for entry in running {
+        let tags = sort_tags(entry.tags.clone());
+        println!(
+            "Running: '{}'{} for {} (started {}, {})",
+            entry.content,
+            if tags.is_empty() {
+                String::new()
+            } else {
+                format!(" [{}]", tags.join(", "))
+            },
+            format_duration((now - entry.timestamp).num_seconds().max(0)),
+            entry.timestamp.format("%H:%M"),
+            entry.id
+        );
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::fs;
+    use std::io::Write;
 
     #[test]
     fn test_log_action() {
         let content = "Test content".to_string();
         let tags = vec!["tag1".to_string(), "tag2".to_string()];
-        let test_json_path = "action_log_test.json";
+        let test_dir = std::env::temp_dir().join("acty_test_log_action");
+        let _ = fs::remove_dir_all(&test_dir);
+        let test_json_path = test_dir.join("action_log.json");
         let config = Config {
-            log_file: test_json_path.to_string(),
+            log_file: test_json_path.to_string_lossy().to_string(),
+            ..Config::default()
         };
 
-        log_action(&config, content, tags);
+        log_action(&config, content, tags, None, None, None);
 
-        let file_content = fs::read_to_string(test_json_path).unwrap();
+        let file_content = fs::read_to_string(&test_json_path).unwrap();
         let log_entries: Vec<LogEntry> = file_content
             .lines()
             .map(|line| serde_json::from_str(line).unwrap())
@@ -233,30 +497,28 @@ mod tests {
         assert!(log_entries[0].tags.contains(&"tag1".to_string()));
         assert!(log_entries[0].tags.contains(&"tag2".to_string()));
 
-        fs::remove_file(test_json_path).unwrap();
+        fs::remove_dir_all(test_dir).unwrap();
     }
 
     #[test]
     fn test_delete_logs() {
-        let test_json_path = "action_log_delete_test.json";
+        let test_dir = std::env::temp_dir().join("acty_test_delete");
+        let _ = fs::remove_dir_all(&test_dir);
+        let test_json_path = test_dir.join("action_log.json");
         let config = Config {
-            log_file: test_json_path.to_string(),
+            log_file: test_json_path.to_string_lossy().to_string(),
+            ..Config::default()
         };
 
-        // Ensure clean state
-        if std::path::Path::new(test_json_path).exists() {
-            fs::remove_file(test_json_path).unwrap();
-        }
-
-        log_action(&config, "Entry 1".to_string(), vec![]);
-        log_action(&config, "Entry 2".to_string(), vec![]);
-        log_action(&config, "Entry 3".to_string(), vec![]);
-        log_action(&config, "Entry 4".to_string(), vec![]);
+        log_action(&config, "Entry 1".to_string(), vec![], None, None, None);
+        log_action(&config, "Entry 2".to_string(), vec![], None, None, None);
+        log_action(&config, "Entry 3".to_string(), vec![], None, None, None);
+        log_action(&config, "Entry 4".to_string(), vec![], None, None, None);
 
         // Delete entries 2 and 4
-        delete_logs(&config, vec![2, 4]);
+        delete_logs(&config, vec!["2".to_string(), "4".to_string()]);
 
-        let file_content = fs::read_to_string(test_json_path).unwrap();
+        let file_content = fs::read_to_string(&test_json_path).unwrap();
         let log_entries: Vec<LogEntry> = file_content
             .lines()
             .map(|line| serde_json::from_str(line).unwrap())
@@ -267,36 +529,36 @@ mod tests {
         assert_eq!(log_entries[1].content, "Entry 3");
 
         // Try deleting invalid index
-        delete_logs(&config, vec![99]);
-        let file_content_after_invalid = fs::read_to_string(test_json_path).unwrap();
+        delete_logs(&config, vec!["99".to_string()]);
+        let file_content_after_invalid = fs::read_to_string(&test_json_path).unwrap();
         assert_eq!(file_content, file_content_after_invalid);
 
-        fs::remove_file(test_json_path).unwrap();
+        fs::remove_dir_all(test_dir).unwrap();
     }
 
     #[test]
     fn test_update_log() {
-        let test_json_path = "action_log_update_test.json";
+        let test_dir = std::env::temp_dir().join("acty_test_update");
+        let _ = fs::remove_dir_all(&test_dir);
+        let test_json_path = test_dir.join("action_log.json");
         let config = Config {
-            log_file: test_json_path.to_string(),
+            log_file: test_json_path.to_string_lossy().to_string(),
+            ..Config::default()
         };
 
-        // Ensure clean state
-        if std::path::Path::new(test_json_path).exists() {
-            fs::remove_file(test_json_path).unwrap();
-        }
-
-        log_action(&config, "Old Content".to_string(), vec!["old_tag".to_string()]);
+        log_action(&config, "Old Content".to_string(), vec!["old_tag".to_string()], None, None, None);
 
         // Update content and tags
         update_log(
-            &config, 
-            1, 
-            "New Content".to_string(), 
-            Some(vec!["new_tag1".to_string(), "new_tag2".to_string()])
+            &config,
+            "1",
+            Some("New Content".to_string()),
+            Some(vec!["new_tag1".to_string(), "new_tag2".to_string()]),
+            None,
+            None,
         );
 
-        let file_content = fs::read_to_string(test_json_path).unwrap();
+        let file_content = fs::read_to_string(&test_json_path).unwrap();
         let log_entries: Vec<LogEntry> = file_content
             .lines()
             .map(|line| serde_json::from_str(line).unwrap())
@@ -309,8 +571,8 @@ mod tests {
         assert!(!log_entries[0].tags.contains(&"old_tag".to_string()));
 
         // Update only content
-        update_log(&config, 1, "New Content 2".to_string(), None);
-        let file_content_2 = fs::read_to_string(test_json_path).unwrap();
+        update_log(&config, "1", Some("New Content 2".to_string()), None, None, None);
+        let file_content_2 = fs::read_to_string(&test_json_path).unwrap();
         let log_entries_2: Vec<LogEntry> = file_content_2
             .lines()
             .map(|line| serde_json::from_str(line).unwrap())
@@ -320,26 +582,72 @@ mod tests {
         // Tags should remain unchanged from previous update
         assert!(log_entries_2[0].tags.contains(&"new_tag1".to_string()));
 
-        fs::remove_file(test_json_path).unwrap();
+        // Notes are replaced by --note and removed by an empty one
+        let notes = |config: &Config| {
+            storage::open(config).read(Store::Active, &Filter::default())[0]
+                .notes
+                .clone()
+        };
+        update_log(&config, "last", None, None, None, Some("- roadmap".to_string()));
+        assert_eq!(notes(&config).as_deref(), Some("- roadmap"));
+        update_log(&config, "last", None, None, None, Some(String::new()));
+        assert_eq!(notes(&config), None);
+        assert!(!fs::read_to_string(&test_json_path).unwrap().contains("notes"));
+
+        fs::remove_dir_all(test_dir).unwrap();
     }
 
     #[test]
-    fn test_copy_log() {
-        let test_json_path = "action_log_copy_test.json";
+    fn test_edit_copy_and_delete_by_id() {
+        let test_dir = std::env::temp_dir().join("acty_test_by_id");
+        let _ = fs::remove_dir_all(&test_dir);
         let config = Config {
-            log_file: test_json_path.to_string(),
+            log_file: test_dir.join("action_log.json").to_string_lossy().to_string(),
+            ..Config::default()
         };
+        let entries = || storage::open(&config).read(Store::Active, &Filter::default());
+
+        log_action(&config, "Entry 1".to_string(), vec![], None, None, None);
+        log_action(&config, "Entry 2".to_string(), vec![], None, None, None);
+        log_action(&config, "Entry 3".to_string(), vec![], None, None, None);
+        let ids: Vec<String> = entries().into_iter().map(|e| e.id).collect();
+
+        // Full IDs and unique prefixes, in any case
+        update_log(&config, &ids[0], Some("First".to_string()), None, None, None);
+        copy_log(&config, &ids[1][..6].to_uppercase(), Some("Copy".to_string()), None);
+        delete_logs(&config, vec![ids[2].clone()]);
+
+        let after = entries();
+        let contents: Vec<&str> = after.iter().map(|e| e.content.as_str()).collect();
+        assert_eq!(contents, vec!["First", "Entry 2", "Copy"]);
+        assert_eq!(after[0].id, ids[0]);
+        assert_eq!(after[1].id, ids[1]);
+        assert!(!ids.contains(&after[2].id));
+
+        // An ID stays with its entry while line numbers shift
+        delete_logs(&config, vec!["1".to_string()]);
+        update_log(&config, &ids[1], Some("Second".to_string()), None, None, None);
+        assert_eq!(entries()[0].content, "Second");
+
+        fs::remove_dir_all(test_dir).unwrap();
+    }
 
-        if std::path::Path::new(test_json_path).exists() {
-            fs::remove_file(test_json_path).unwrap();
-        }
+    #[test]
+    fn test_copy_log() {
+        let test_dir = std::env::temp_dir().join("acty_test_copy");
+        let _ = fs::remove_dir_all(&test_dir);
+        let test_json_path = test_dir.join("action_log.json");
+        let config = Config {
+            log_file: test_json_path.to_string_lossy().to_string(),
+            ..Config::default()
+        };
 
-        log_action(&config, "Original Content".to_string(), vec!["tag1".to_string()]);
+        log_action(&config, "Original Content".to_string(), vec!["tag1".to_string()], None, None, None);
 
         // Copy with same content
-        copy_log(&config, 1, None);
+        copy_log(&config, "1", None, None);
 
-        let contents = fs::read_to_string(test_json_path).unwrap();
+        let contents = fs::read_to_string(&test_json_path).unwrap();
         let entries: Vec<LogEntry> = contents.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
 
         assert_eq!(entries.len(), 2);
@@ -348,16 +656,16 @@ mod tests {
         assert!(entries[1].timestamp > entries[0].timestamp);
 
         // Copy with new content
-        copy_log(&config, 1, Some("New Content".to_string()));
+        copy_log(&config, "1", Some("New Content".to_string()), None);
         
-        let contents_2 = fs::read_to_string(test_json_path).unwrap();
+        let contents_2 = fs::read_to_string(&test_json_path).unwrap();
         let entries_2: Vec<LogEntry> = contents_2.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
 
         assert_eq!(entries_2.len(), 3);
         assert_eq!(entries_2[2].content, "New Content");
         assert_eq!(entries_2[2].tags, vec!["tag1".to_string()]); // Tags preserved
 
-        fs::remove_file(test_json_path).unwrap();
+        fs::remove_dir_all(test_dir).unwrap();
     }
 
     #[test]
@@ -369,22 +677,15 @@ mod tests {
         
         let config = Config {
             log_file: log_path.to_string_lossy().to_string(),
+            ..Config::default()
         };
 
         // Create logs manually
         let old_date = Local::now() - chrono::Duration::days(10);
         let new_date = Local::now();
 
-        let old_entry = LogEntry {
-            timestamp: old_date,
-            content: "Old Log".to_string(),
-            tags: vec![],
-        };
-        let new_entry = LogEntry {
-            timestamp: new_date,
-            content: "New Log".to_string(),
-            tags: vec![],
-        };
+        let old_entry = LogEntry::new(old_date, "Old Log".to_string(), vec![]);
+        let new_entry = LogEntry::new(new_date, "New Log".to_string(), vec![]);
 
         {
             let mut file = std::fs::File::create(&log_path).unwrap();
@@ -409,4 +710,236 @@ mod tests {
 
         std::fs::remove_dir_all(test_dir).unwrap();
     }
+
+    #[test]
+    fn test_archived_entries_by_selector() {
+        let test_dir = std::env::temp_dir().join("acty_test_archived_selectors");
+        let _ = std::fs::remove_dir_all(&test_dir);
+        let config = Config {
+            log_file: test_dir.join("action_log.json").to_string_lossy().to_string(),
+            ..Config::default()
+        };
+
+        let old = Local::now() - chrono::Duration::days(10);
+        log_action(&config, "Old one".to_string(), vec![], None, Some(old), None);
+        log_action(&config, "Old two".to_string(), vec![], None, Some(old + chrono::Duration::hours(1)), None);
+        log_action(&config, "New".to_string(), vec![], None, None, None);
+        archive_logs(&config, 7);
+
+        assert_eq!(split_selector("A:k3x9"), (Store::Archive, "k3x9"));
+        assert_eq!(split_selector("k3x9"), (Store::Active, "k3x9"));
+
+        update_log(&config, "a:1", Some("Old one (edited)".to_string()), None, None, None);
+        copy_log(&config, "a:last", None, None);
+        delete_logs(&config, vec!["a:2".to_string(), "1".to_string()]);
+
+        let storage = storage::open(&config);
+        let archived = storage.read(Store::Archive, &Filter::default());
+        assert_eq!(archived.len(), 1);
+        assert_eq!(archived[0].content, "Old one (edited)");
+        let active: Vec<String> = storage
+            .read(Store::Active, &Filter::default())
+            .into_iter()
+            .map(|e| e.content)
+            .collect();
+        assert_eq!(active, vec!["Old two"]);
+        assert_eq!(storage.read(Store::Trash, &Filter::default()).len(), 2);
+
+        let all: Vec<String> = storage::read_scope(storage.as_ref(), storage::Scope::All, &Filter::default())
+            .into_iter()
+            .map(|(store, e)| store.display_id(&e.id))
+            .collect();
+        assert_eq!(all, vec![format!("a:{}", archived[0].id), storage.read(Store::Active, &Filter::default())[0].id.clone()]);
+
+        std::fs::remove_dir_all(test_dir).unwrap();
+    }
+
+    #[test]
+    fn test_unarchive_logs() {
+        let test_dir = std::env::temp_dir().join("acty_test_unarchive");
+        let _ = std::fs::remove_dir_all(&test_dir);
+        let config = Config {
+            log_file: test_dir.join("action_log.json").to_string_lossy().to_string(),
+            ..Config::default()
+        };
+
+        let old = Local::now() - chrono::Duration::days(10);
+        log_action(&config, "Old work".to_string(), vec!["work".to_string()], None, Some(old), None);
+        log_action(&config, "Old rest".to_string(), vec!["rest".to_string()], None, Some(old + chrono::Duration::hours(1)), None);
+        log_action(&config, "New".to_string(), vec![], None, None, None);
+        archive_logs(&config, 7);
+
+        let work = Filter {
+            tags: vec!["work".to_string()],
+            ..Filter::default()
+        };
+        unarchive_logs(&config, work.clone(), true);
+        let storage = storage::open(&config);
+        assert_eq!(storage.read(Store::Archive, &Filter::default()).len(), 2);
+
+        unarchive_logs(&config, work, false);
+        let active: Vec<String> = storage
+            .read(Store::Active, &Filter::default())
+            .into_iter()
+            .map(|e| e.content)
+            .collect();
+        assert_eq!(active, vec!["Old work", "New"]);
+        assert_eq!(storage.read(Store::Archive, &Filter::default()).len(), 1);
+
+        std::fs::remove_dir_all(test_dir).unwrap();
+    }
+
+    #[test]
+    fn test_resolve_id() {
+        let ids = vec![
+            "k3x9ab01".to_string(),
+            "k3y7cd02".to_string(),
+            "m0pq9z03".to_string(),
+        ];
+
+        assert_eq!(resolve_id(&ids, "last"), Ok(2));
+        assert_eq!(resolve_id(&ids, "1"), Ok(0));
+        assert_eq!(resolve_id(&ids, "m0"), Ok(2));
+        assert_eq!(resolve_id(&ids, "K3Y"), Ok(1));
+        assert!(resolve_id(&ids, "k3").unwrap_err().contains("ambiguous"));
+        assert!(resolve_id(&ids, "4").is_err());
+        assert!(resolve_id(&ids, "zz").is_err());
+        assert!(resolve_id(&[], "last").is_err());
+    }
+
+    #[test]
+    fn test_tags_keep_their_order() {
+        let test_dir = std::env::temp_dir().join("acty_test_tag_order");
+        let _ = fs::remove_dir_all(&test_dir);
+        let config = Config {
+            log_file: test_dir.join("action_log.json").to_string_lossy().to_string(),
+            default_tags: vec!["laptop".to_string()],
+            ..Config::default()
+        };
+        let start = Local::now() - chrono::Duration::hours(1);
+
+        // Like `acty log -t b,a`: `report --split first` has to credit b
+        log_action(&config, "Start".to_string(), vec![], None, Some(start), None);
+        log_action(&config, "Work".to_string(), vec!["b".to_string(), "a".to_string(), "b".to_string()], Some(600), None, None);
+        start_timer(&config, "More".to_string(), vec!["z".to_string(), "y".to_string()]);
+        let entries = storage::open(&config).read(Store::Active, &Filter::default());
+        assert_eq!(entries[1].tags, vec!["b", "a", "laptop"]);
+        assert_eq!(entries[2].tags, vec!["z", "y", "laptop"]);
+
+        let options = crate::report::ReportOptions {
+            split: crate::report::Split::First,
+            by: crate::report::Breakdown::Total,
+            combinations: false,
+            depth: None,
+        };
+        let rows = crate::report::build_report(&[(&entries[1], 600)], &options, chrono::Weekday::Mon);
+        assert_eq!(rows[1].tags, vec!["b"]);
+        assert_eq!(rows[1].seconds, 600);
+        assert_eq!(rows.len(), 2);
+
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+
+    #[test]
+    fn test_ids_survive_deletion_and_legacy_migration() {
+        let test_dir = std::env::temp_dir().join("acty_test_stable_ids");
+        std::fs::create_dir_all(&test_dir).unwrap();
+        let log_path = test_dir.join("action_log.json");
+
+        let config = Config {
+            log_file: log_path.to_string_lossy().to_string(),
+            ..Config::default()
+        };
+
+        {
+            let mut file = std::fs::File::create(&log_path).unwrap();
+            writeln!(file, r#"{{"timestamp":"2024-01-01T09:00:00+00:00","content":"Legacy 1","tags":[]}}"#).unwrap();
+            writeln!(file, r#"{{"timestamp":"2024-01-01T10:00:00+00:00","content":"Legacy 2","tags":[]}}"#).unwrap();
+        }
+
+        // Reading assigns the same IDs every time without writing the file
+        let before = fs::read_to_string(&log_path).unwrap();
+        let entries = storage::open(&config).read(Store::Active, &Filter::default());
+        assert!(entries.iter().all(|e| !e.id.is_empty()));
+        assert_eq!(storage::open(&config).read(Store::Active, &Filter::default()), entries);
+        assert_eq!(fs::read_to_string(&log_path).unwrap(), before);
+        let second_id = entries[1].id.clone();
+
+        // Deleting the first entry must not change how the second is addressed
+        delete_logs(&config, vec![entries[0].id.clone()]);
+        update_log(&config, &second_id[..4], Some("Renamed".to_string()), None, None, None);
+
+        let remaining: Vec<LogEntry> = fs::read_to_string(&log_path)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].id, second_id);
+        assert_eq!(remaining[0].content, "Renamed");
+
+        std::fs::remove_dir_all(test_dir).unwrap();
+    }
+
+    #[test]
+    fn test_timers() {
+        let test_dir = std::env::temp_dir().join("acty_test_timers");
+        let _ = fs::remove_dir_all(&test_dir);
+        let config = Config {
+            log_file: test_dir.join("action_log.json").to_string_lossy().to_string(),
+            ..Config::default()
+        };
+        let read = || storage::open(&config).read(Store::Active, &Filter::default());
+
+        log_action(&config, "Planned".to_string(), vec![], Some(1500), None, None);
+        start_timer(&config, "First".to_string(), vec!["work".to_string()]);
+        assert!(read()[1].running);
+
+        // Starting another timer stops the running one
+        start_timer(&config, "Second".to_string(), vec![]);
+        let entries = read();
+        assert_eq!(entries[0].duration, Some(1500));
+        assert!(!entries[1].running);
+        assert!(entries[1].duration.is_some());
+        assert!(entries[2].running);
+
+        stop_timer(&config);
+        let entries = read();
+        assert!(entries.iter().all(|e| !e.running));
+        assert!(entries[2].duration.is_some());
+
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+
+    #[test]
+    fn test_backdated_entries_are_sorted() {
+        let test_dir = std::env::temp_dir().join("acty_test_backdated");
+        let _ = fs::remove_dir_all(&test_dir);
+        let config = Config {
+            log_file: test_dir.join("action_log.json").to_string_lossy().to_string(),
+            ..Config::default()
+        };
+        let read = || -> Vec<String> {
+            storage::open(&config)
+                .read(Store::Active, &Filter::default())
+                .into_iter()
+                .map(|e| e.content)
+                .collect()
+        };
+
+        let now = Local::now();
+        log_action(&config, "Second".to_string(), vec![], None, Some(now - chrono::Duration::hours(1)), None);
+        log_action(&config, "Third".to_string(), vec![], None, None, None);
+        log_action(&config, "First".to_string(), vec![], None, Some(now - chrono::Duration::hours(2)), None);
+        assert_eq!(read(), vec!["First", "Second", "Third"]);
+
+        copy_log(&config, "3", None, Some(now - chrono::Duration::minutes(90)));
+        assert_eq!(read(), vec!["First", "Third", "Second", "Third"]);
+
+        // Moving an entry in time keeps its content
+        update_log(&config, "1", None, None, Some(now - chrono::Duration::minutes(30)), None);
+        assert_eq!(read(), vec!["Third", "Second", "First", "Third"]);
+
+        fs::remove_dir_all(test_dir).unwrap();
+    }
 }
@@ -1,52 +1,103 @@
 use crate::config::Config;
 use crate::log_entry::LogEntry;
-use crate::util::{should_include_log, sort_tags};
-use std::fs::File;
-use std::io::{BufRead, BufReader};
-
-pub fn output_markdown_table(
-    config: &Config,
-    date: Option<String>,
-    range: Option<i64>,
-    tags: Vec<String>,
-    search: Option<String>,
-    use_archive: bool,
-) {
-    let path = if use_archive {
-        std::path::Path::new(&config.log_file).with_file_name("archive.json")
-    } else {
-        std::path::PathBuf::from(&config.log_file)
-    };
+use crate::storage::{self, Scope, Store};
+use crate::util::{format_duration, sort_tags, Filter};
+use chrono::Local;
 
-    let file = match File::open(&path) {
-        Ok(f) => f,
-        Err(_) => {
-            println!("No logs found.");
-            return;
-        }
+pub fn output_markdown_table(config: &Config, filter: Filter, scope: Scope) {
+    let entries = {
+        let _lock = storage::lock_shared(config);
+        storage::read_scope_timed(storage::open(config).as_ref(), scope, &filter, Local::now())
     };
-    let reader = BufReader::new(file);
-
-    println!("| Date       | Time     | Tags          | Content |");
-    println!("|------------|----------|---------------|---------|");
 
-    for line in reader.lines() {
-        let log_entry: LogEntry =
-            serde_json::from_str(&line.expect("Unable to read the log entry"))
-                .expect("Unable to deserialize the log entry");
+    if entries.is_empty() {
+        println!("No logs found.");
+        return;
+    }
+    print!("{}", render(config, &entries));
+}
 
-        if !should_include_log(&log_entry, &date, &range, &tags, &search) {
-            continue;
-        }
+/// The entries as a Markdown table, followed by their notes as footnotes.
+fn render(config: &Config, entries: &[(Store, LogEntry, Option<i64>)]) -> String {
+    let mut text = String::new();
+    text.push_str("| Date       | Time     | Duration | Tags          | Content |\n");
+    text.push_str("|------------|----------|----------|---------------|---------|\n");
 
+    for (_, log_entry, duration) in entries {
         let sorted_tags = sort_tags(log_entry.tags.clone());
+        // Notes don't fit in a table cell, so they become footnotes
+        let reference = match &log_entry.notes {
+            Some(_) => format!(" [^{}]", log_entry.id),
+            None => String::new(),
+        };
 
-        println!(
-            "| {} | {} | {} | {} |",
-            log_entry.timestamp.format("%Y-%m-%d"),
+        text.push_str(&format!(
+            "| {} | {} | {} | {} | {}{} |\n",
+            log_entry.timestamp.format(&config.date_format),
             log_entry.timestamp.format("%H:%M:%S"),
+            duration.map(format_duration).unwrap_or_else(|| "-".to_string()),
             sorted_tags.join(", "),
-            log_entry.content.replace("|", "\\|"),
+            log_entry.content.replace('|', "\\|"),
+            reference,
+        ));
+    }
+
+    for (_, log_entry, _) in entries {
+        if let Some(notes) = &log_entry.notes {
+            text.push('\n');
+            text.push_str(&footnote(&log_entry.id, notes));
+        }
+    }
+    text
+}
+
+/// A footnote definition holding `notes`; lines after the first are
+/// indented so they stay part of it.
+fn footnote(label: &str, notes: &str) -> String {
+    let mut text = format!("[^{}]:", label);
+    for (index, line) in notes.lines().enumerate() {
+        if line.is_empty() {
+            text.push('\n');
+        } else if index == 0 {
+            text.push_str(&format!(" {}\n", line));
+        } else {
+            text.push_str(&format!("    {}\n", line));
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_footnote() {
+        assert_eq!(footnote("k3x9ab01", "One line"), "[^k3x9ab01]: One line\n");
+        assert_eq!(
+            footnote("k3x9ab01", "- roadmap\n\n- hiring"),
+            "[^k3x9ab01]: - roadmap\n\n    - hiring\n"
         );
     }
+
+    #[test]
+    fn test_render() {
+        let at = Local::now();
+        let mut coffee = LogEntry::new(at, "Coffee | cake".to_string(), vec!["break".to_string()]);
+        coffee.notes = Some("with Sam\nabout hiring".to_string());
+        let standup = LogEntry::new(at, "Standup".to_string(), vec![]);
+        let entries = vec![
+            (Store::Active, coffee.clone(), Some(900)),
+            (Store::Active, standup.clone(), None),
+        ];
+
+        let text = render(&Config::default(), &entries);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 7);
+        assert!(lines[2].ends_with(&format!("| break | Coffee \\| cake [^{}] |", coffee.id)));
+        assert!(lines[3].ends_with("| - |  | Standup |"));
+        assert_eq!(lines[4], "");
+        assert_eq!(lines[5], format!("[^{}]: with Sam", coffee.id));
+        assert_eq!(lines[6], "    about hiring");
+    }
 }
@@ -0,0 +1,107 @@
+use crate::config::Config;
+use crate::log_entry::{LogEntry, ENTRY_SCHEMA_VERSION};
+use crate::storage::{self, Store};
+use crate::util::Filter;
+use chrono::Local;
+
+const STORES: [Store; 3] = [Store::Active, Store::Archive, Store::Trash];
+
+/// Upgrades every stored entry to the current schema version in place. The
+/// files are backed up before anything reads or repairs them, and the backups
+/// are dropped again if there was nothing to upgrade.
+pub fn migrate(config: &Config) {
+    let _lock = storage::lock_unrecovered(config);
+    let storage = storage::open(config);
+    let backups = storage.backup(&format!("bak-{}", Local::now().format("%Y%m%d%H%M%S")));
+    storage.recover();
+
+    let mut outdated = 0;
+    let mut newer = 0;
+    for store in STORES {
+        for entry in storage.read(store, &Filter::default()) {
+            if entry.schema_version < ENTRY_SCHEMA_VERSION {
+                outdated += 1;
+            } else if entry.schema_version > ENTRY_SCHEMA_VERSION {
+                newer += 1;
+            }
+        }
+    }
+
+    if newer > 0 {
+        eprintln!(
+            "{} entry(ies) were written by a newer version of acty and are left as they are.",
+            newer
+        );
+    }
+    if outdated == 0 {
+        for backup in &backups {
+            let _ = std::fs::remove_file(backup);
+        }
+        println!("Everything is at schema version {} already.", ENTRY_SCHEMA_VERSION);
+        return;
+    }
+
+    let migrated: usize = STORES
+        .iter()
+        .map(|&store| storage.rewrite(store, &mut LogEntry::upgrade))
+        .sum();
+
+    println!(
+        "Migrated {} entry(ies) to schema version {}.",
+        migrated, ENTRY_SCHEMA_VERSION
+    );
+    println!("Backups:");
+    for backup in backups {
+        println!("  {}", backup.display());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_migrate() {
+        let test_dir = std::env::temp_dir().join("acty_test_migrate");
+        let _ = fs::remove_dir_all(&test_dir);
+        fs::create_dir_all(&test_dir).unwrap();
+        let log_path = test_dir.join("action_log.json");
+        let legacy = r#"{"timestamp":"2024-01-01T09:00:00+00:00","content":"Old","tags":[],"mood":"happy"}"#;
+        let newer = r#"{"id":"k3x9ab01","timestamp":"2024-01-02T09:00:00+00:00","content":"New","tags":[],"schema_version":7}"#;
+        let raw = format!("{}\n{}\nnot json\n", legacy, newer);
+        fs::write(&log_path, &raw).unwrap();
+        let config = Config {
+            log_file: log_path.to_string_lossy().to_string(),
+            ..Config::default()
+        };
+
+        migrate(&config);
+
+        let entries = storage::open(&config).read(Store::Active, &Filter::default());
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].schema_version, ENTRY_SCHEMA_VERSION);
+        assert_eq!(entries[0].extra["mood"], "happy");
+        // Entries from a newer acty are left alone
+        assert_eq!(entries[1].schema_version, 7);
+        assert!(fs::read_to_string(&log_path).unwrap().ends_with("\nnot json\n"));
+
+        // The backup holds the log byte for byte as it was before the upgrade
+        let backups = || {
+            fs::read_dir(&test_dir)
+                .unwrap()
+                .map(|e| e.unwrap().path())
+                .filter(|p| p.to_string_lossy().contains(".bak-"))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(backups().len(), 1);
+        assert_eq!(fs::read_to_string(&backups()[0]).unwrap(), raw);
+
+        // Nothing left to upgrade, so no new backup is kept
+        fs::remove_file(&backups()[0]).unwrap();
+        migrate(&config);
+        assert!(backups().is_empty());
+
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+}
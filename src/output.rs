@@ -0,0 +1,188 @@
+use crate::config::Config;
+use crate::log_entry::LogEntry;
+use crate::storage::Store;
+use crate::util::sort_tags;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Bumped whenever a field is removed or changes meaning. New fields may be
+/// added without a bump, so consumers should ignore fields they don't know.
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    #[default]
+    Table,
+    Json,
+    Jsonl,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "table" => Ok(OutputFormat::Table),
+            "json" => Ok(OutputFormat::Json),
+            "jsonl" => Ok(OutputFormat::Jsonl),
+            other => Err(format!("Unknown output format: {} (expected table, json or jsonl)", other)),
+        }
+    }
+}
+
+/// ANSI color codes for table output.
+pub const ID_COLOR: &str = "33";
+pub const TAG_COLOR: &str = "36";
+
+/// Wraps `text` in an ANSI color when the config asks for colored output.
+pub fn paint(config: &Config, color: &str, text: &str) -> String {
+    if config.use_color() && !text.is_empty() {
+        format!("\x1b[{}m{}\x1b[0m", color, text)
+    } else {
+        text.to_string()
+    }
+}
+
+/// One log entry as emitted by `--output json`/`jsonl`.
+#[derive(Serialize, Debug)]
+pub struct EntryRecord {
+    pub id: String,
+    /// `active`, `archive` or `trash`.
+    pub store: Store,
+    /// RFC 3339 with the local offset.
+    pub timestamp: String,
+    /// Time attributed to the entry, as shown in the Duration column of `list`.
+    pub duration_seconds: Option<i64>,
+    /// `recorded` (a timer or `--for`), `running` (elapsed so far), `gap`
+    /// (since the previous entry ended) or null for the first entry.
+    pub duration_source: Option<&'static str>,
+    pub tags: Vec<String>,
+    pub content: String,
+    pub notes: Option<String>,
+    /// Only for entries in the trash.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<String>,
+}
+
+impl EntryRecord {
+    pub fn new(store: Store, entry: &LogEntry, duration: Option<i64>) -> Self {
+        let duration_source = match (duration, entry.running, entry.duration) {
+            (None, _, _) => None,
+            (Some(_), true, _) => Some("running"),
+            (Some(_), false, Some(_)) => Some("recorded"),
+            (Some(_), false, None) => Some("gap"),
+        };
+        EntryRecord {
+            id: entry.id.clone(),
+            store,
+            timestamp: entry.timestamp.to_rfc3339(),
+            duration_seconds: duration,
+            duration_source,
+            tags: sort_tags(entry.tags.clone()),
+            content: entry.content.clone(),
+            notes: entry.notes.clone(),
+            deleted_at: entry.deleted_at.map(|d| d.to_rfc3339()),
+        }
+    }
+}
+
+pub fn entry_records(entries: &[(Store, LogEntry, Option<i64>)]) -> Vec<EntryRecord> {
+    entries
+        .iter()
+        .map(|(store, entry, duration)| EntryRecord::new(*store, entry, *duration))
+        .collect()
+}
+
+#[derive(Serialize)]
+struct Document<'a, T> {
+    schema_version: u32,
+    #[serde(rename = "type")]
+    kind: &'a str,
+    data: &'a [T],
+}
+
+#[derive(Serialize)]
+struct Line<'a, T> {
+    schema_version: u32,
+    #[serde(rename = "type")]
+    kind: &'a str,
+    #[serde(flatten)]
+    item: &'a T,
+}
+
+/// Renders `items` as a single JSON document (`{"schema_version", "type":
+/// kind, "data": [...]}`) or as JSON lines, one object per item tagged with
+/// `item_kind`.
+pub fn render_json<T: Serialize>(
+    format: OutputFormat,
+    kind: &str,
+    item_kind: &str,
+    items: &[T],
+) -> String {
+    match format {
+        OutputFormat::Jsonl => items
+            .iter()
+            .map(|item| {
+                let line = Line {
+                    schema_version: SCHEMA_VERSION,
+                    kind: item_kind,
+                    item,
+                };
+                serde_json::to_string(&line).expect("Unable to serialize output") + "\n"
+            })
+            .collect(),
+        _ => {
+            let document = Document {
+                schema_version: SCHEMA_VERSION,
+                kind,
+                data: items,
+            };
+            serde_json::to_string_pretty(&document).expect("Unable to serialize output") + "\n"
+        }
+    }
+}
+
+pub fn print_json<T: Serialize>(format: OutputFormat, kind: &str, item_kind: &str, items: &[T]) {
+    print!("{}", render_json(format, kind, item_kind, items));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Local};
+
+    #[test]
+    fn test_render_entries() {
+        let start = Local::now() - Duration::hours(1);
+        let first = LogEntry::new(start, "First".to_string(), vec!["work".to_string()]);
+        let second = LogEntry::new(start + Duration::minutes(5), "Second".to_string(), vec![]);
+        let records = entry_records(&[
+            (Store::Active, first.clone(), None),
+            (Store::Archive, second, Some(300)),
+        ]);
+
+        let json: serde_json::Value =
+            serde_json::from_str(&render_json(OutputFormat::Json, "entries", "entry", &records))
+                .unwrap();
+        assert_eq!(json["schema_version"], SCHEMA_VERSION);
+        assert_eq!(json["type"], "entries");
+        assert_eq!(json["data"][0]["id"], first.id.as_str());
+        assert_eq!(json["data"][0]["store"], "active");
+        assert_eq!(json["data"][1]["store"], "archive");
+        assert_eq!(json["data"][0]["tags"][0], "work");
+        assert!(json["data"][0]["duration_seconds"].is_null());
+        assert_eq!(json["data"][1]["duration_seconds"], 300);
+        assert_eq!(json["data"][1]["duration_source"], "gap");
+
+        let jsonl = render_json(OutputFormat::Jsonl, "entries", "entry", &records);
+        let lines: Vec<serde_json::Value> = jsonl
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1]["type"], "entry");
+        assert_eq!(lines[1]["schema_version"], SCHEMA_VERSION);
+        assert_eq!(lines[1]["content"], "Second");
+    }
+}
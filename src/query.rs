@@ -0,0 +1,456 @@
+use crate::config::Config;
+use crate::dates::parse_timestamp;
+use crate::log_entry::LogEntry;
+use crate::util::tag_matches;
+use chrono::{DateTime, Local, NaiveDate};
+
+/// A parsed `--query` expression, e.g.
+/// `tag:work and not tag:break and (content:"deploy" or tag:ops) after:2026-10-01`.
+///
+/// Terms next to each other are joined with `and`. `and` binds tighter than
+/// `or`, and parentheses group. Supported terms:
+///
+/// * `tag:NAME` – the entry has the tag or one below it (`tag:work` matches
+///   `work/clientA`). Tag aliases are expanded and case matters, as with `-t`
+/// * `content:TEXT` – the content contains TEXT (case-insensitive)
+/// * `id:PREFIX` – the entry ID starts with PREFIX
+/// * `on:DATE` – the entry is on that day
+/// * `after:TIME` – the entry is at or after TIME (a date means its start)
+/// * `before:TIME` – the entry is before TIME
+/// * a bare word or `"quoted text"` – content, a tag or the notes contain it
+#[derive(Debug, Clone)]
+pub struct Query {
+    expr: Expr,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Tag(String),
+    Content(String),
+    Id(String),
+    On(NaiveDate),
+    After(DateTime<Local>),
+    Before(DateTime<Local>),
+    Text(String),
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    LParen,
+    RParen,
+    And,
+    Or,
+    Not,
+    Term { field: Option<String>, value: String },
+}
+
+impl Query {
+    pub fn parse(input: &str, now: DateTime<Local>, config: &Config) -> Result<Query, String> {
+        let tokens = tokenize(input).map_err(|e| format!("Invalid query: {}", e))?;
+        let mut parser = Parser {
+            tokens,
+            pos: 0,
+            now,
+            input_len: input.chars().count(),
+        };
+        if parser.tokens.is_empty() {
+            return Err("Invalid query: the query is empty".to_string());
+        }
+        let mut expr = parser.parse_or().map_err(|e| format!("Invalid query: {}", e))?;
+        if let Some((token, column)) = parser.tokens.get(parser.pos) {
+            return Err(format!(
+                "Invalid query: unexpected {} at position {}",
+                describe(token),
+                column
+            ));
+        }
+        expr.resolve_tags(config);
+        Ok(Query { expr })
+    }
+
+    pub fn matches(&self, entry: &LogEntry) -> bool {
+        self.expr.eval(entry)
+    }
+}
+
+impl Expr {
+    /// Expands the tag aliases in `tag:` terms.
+    fn resolve_tags(&mut self, config: &Config) {
+        match self {
+            Expr::And(a, b) | Expr::Or(a, b) => {
+                a.resolve_tags(config);
+                b.resolve_tags(config);
+            }
+            Expr::Not(a) => a.resolve_tags(config),
+            Expr::Tag(tag) => *tag = config.resolve_tag(tag),
+            _ => {}
+        }
+    }
+
+    fn eval(&self, entry: &LogEntry) -> bool {
+        match self {
+            Expr::And(a, b) => a.eval(entry) && b.eval(entry),
+            Expr::Or(a, b) => a.eval(entry) || b.eval(entry),
+            Expr::Not(a) => !a.eval(entry),
+            Expr::Tag(tag) => entry.tags.iter().any(|t| tag_matches(t, tag)),
+            Expr::Content(text) => entry.content.to_lowercase().contains(text),
+            Expr::Id(prefix) => entry.id.starts_with(prefix.as_str()),
+            Expr::On(date) => entry.timestamp.date_naive() == *date,
+            Expr::After(time) => entry.timestamp >= *time,
+            Expr::Before(time) => entry.timestamp < *time,
+            Expr::Text(text) => {
+                entry.content.to_lowercase().contains(text)
+                    || entry.tags.iter().any(|t| t.to_lowercase().contains(text))
+                    || entry
+                        .notes
+                        .as_ref()
+                        .is_some_and(|n| n.to_lowercase().contains(text))
+            }
+        }
+    }
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::LParen => "'('".to_string(),
+        Token::RParen => "')'".to_string(),
+        Token::And => "'and'".to_string(),
+        Token::Or => "'or'".to_string(),
+        Token::Not => "'not'".to_string(),
+        Token::Term { field: Some(f), value } => format!("'{}:{}'", f, value),
+        Token::Term { field: None, value } => format!("'{}'", value),
+    }
+}
+
+/// Splits the input into tokens, each paired with its 1-based column.
+fn tokenize(input: &str) -> Result<Vec<(Token, usize)>, String> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let column = i + 1;
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        if c == '(' {
+            tokens.push((Token::LParen, column));
+            i += 1;
+            continue;
+        }
+        if c == ')' {
+            tokens.push((Token::RParen, column));
+            i += 1;
+            continue;
+        }
+
+        if c == '"' {
+            let (value, next) = read_quoted(&chars, i)?;
+            tokens.push((Token::Term { field: None, value }, column));
+            i = next;
+            continue;
+        }
+
+        let mut word = String::new();
+        while i < chars.len() && !chars[i].is_whitespace() && chars[i] != '(' && chars[i] != ')' {
+            if chars[i] == ':' {
+                break;
+            }
+            word.push(chars[i]);
+            i += 1;
+        }
+
+        if i < chars.len() && chars[i] == ':' {
+            i += 1;
+            let value = if i < chars.len() && chars[i] == '"' {
+                let (value, next) = read_quoted(&chars, i)?;
+                i = next;
+                value
+            } else {
+                let mut value = String::new();
+                while i < chars.len() && !chars[i].is_whitespace() && chars[i] != '(' && chars[i] != ')' {
+                    value.push(chars[i]);
+                    i += 1;
+                }
+                value
+            };
+            if value.is_empty() {
+                return Err(format!("missing value for '{}:' at position {}", word, column));
+            }
+            tokens.push((
+                Token::Term {
+                    field: Some(word.to_lowercase()),
+                    value,
+                },
+                column,
+            ));
+            continue;
+        }
+
+        let token = match word.to_lowercase().as_str() {
+            "and" => Token::And,
+            "or" => Token::Or,
+            "not" => Token::Not,
+            _ => Token::Term {
+                field: None,
+                value: word,
+            },
+        };
+        tokens.push((token, column));
+    }
+
+    Ok(tokens)
+}
+
+/// Reads a double-quoted string starting at `start`, returning its contents
+/// and the index after the closing quote. `\"` and `\\` are escapes.
+fn read_quoted(chars: &[char], start: usize) -> Result<(String, usize), String> {
+    let mut value = String::new();
+    let mut i = start + 1;
+    while i < chars.len() {
+        match chars[i] {
+            '"' => return Ok((value, i + 1)),
+            '\\' if i + 1 < chars.len() => {
+                value.push(chars[i + 1]);
+                i += 2;
+            }
+            c => {
+                value.push(c);
+                i += 1;
+            }
+        }
+    }
+    Err(format!("unterminated quote starting at position {}", start + 1))
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    now: DateTime<Local>,
+    input_len: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+
+    fn end_position(&self) -> usize {
+        self.input_len + 1
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, String> {
+        let mut left = self.parse_and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            let right = self.parse_and()?;
+            left = Expr::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, String> {
+        let mut left = self.parse_unary()?;
+        loop {
+            match self.peek() {
+                Some(Token::And) => {
+                    self.pos += 1;
+                }
+                // Juxtaposed terms are an implicit `and`
+                Some(Token::Not) | Some(Token::LParen) | Some(Token::Term { .. }) => {}
+                _ => break,
+            }
+            let right = self.parse_unary()?;
+            left = Expr::And(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, String> {
+        if self.peek() == Some(&Token::Not) {
+            self.pos += 1;
+            return Ok(Expr::Not(Box::new(self.parse_unary()?)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, String> {
+        let (token, column) = match self.tokens.get(self.pos) {
+            Some((token, column)) => (token, *column),
+            None => {
+                return Err(format!(
+                    "expected a term at position {} but the query ended",
+                    self.end_position()
+                ))
+            }
+        };
+
+        match token {
+            Token::LParen => {
+                self.pos += 1;
+                let expr = self.parse_or()?;
+                match self.tokens.get(self.pos) {
+                    Some((Token::RParen, _)) => {
+                        self.pos += 1;
+                        Ok(expr)
+                    }
+                    Some((other, at)) => Err(format!(
+                        "expected ')' to close '(' at position {} but found {} at position {}",
+                        column,
+                        describe(other),
+                        at
+                    )),
+                    None => Err(format!("missing ')' to close '(' at position {}", column)),
+                }
+            }
+            Token::Term { field, value } => {
+                let expr = self.term(field.as_deref(), value, column)?;
+                self.pos += 1;
+                Ok(expr)
+            }
+            other => Err(format!(
+                "expected a term but found {} at position {}",
+                describe(other),
+                column
+            )),
+        }
+    }
+
+    fn term(&self, field: Option<&str>, value: &str, column: usize) -> Result<Expr, String> {
+        let time = |value: &str| {
+            parse_timestamp(value, self.now)
+                .map_err(|e| format!("{} (at position {})", e, column))
+        };
+
+        match field {
+            None => Ok(Expr::Text(value.to_lowercase())),
+            Some("tag") => Ok(Expr::Tag(value.to_string())),
+            Some("content") => Ok(Expr::Content(value.to_lowercase())),
+            Some("id") => Ok(Expr::Id(value.to_lowercase())),
+            Some("on") => Ok(Expr::On(time(value)?.date_naive())),
+            Some("after") => Ok(Expr::After(time(value)?)),
+            Some("before") => Ok(Expr::Before(time(value)?)),
+            Some(other) => Err(format!(
+                "unknown field '{}' at position {} (expected tag, content, id, on, after or before)",
+                other, column
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn entry(day: u32, content: &str, tags: &[&str]) -> LogEntry {
+        LogEntry::new(
+            Local.with_ymd_and_hms(2026, 10, day, 10, 0, 0).unwrap(),
+            content.to_string(),
+            tags.iter().map(|t| t.to_string()).collect(),
+        )
+    }
+
+    fn query(input: &str) -> Result<Query, String> {
+        let now = Local.with_ymd_and_hms(2026, 10, 18, 12, 0, 0).unwrap();
+        Query::parse(input, now, &Config::default())
+    }
+
+    #[test]
+    fn test_query_matching() {
+        let q = query(
+            r#"tag:work and not tag:break and (content:"deploy" or tag:ops) after:2026-10-01 before:2026-10-15"#,
+        )
+        .unwrap();
+
+        assert!(q.matches(&entry(5, "Deploy the API", &["work"])));
+        assert!(q.matches(&entry(5, "Rotate keys", &["work", "ops"])));
+        assert!(!q.matches(&entry(5, "Deploy the API", &["work", "break"])));
+        assert!(!q.matches(&entry(5, "Write docs", &["work"])));
+        assert!(!q.matches(&entry(15, "Deploy the API", &["work"])));
+        assert!(q.matches(&entry(1, "Deploy the API", &["work"])));
+    }
+
+    #[test]
+    fn test_query_precedence_and_text() {
+        // `and` binds tighter than `or`
+        let q = query("tag:a or tag:b tag:c").unwrap();
+        assert!(q.matches(&entry(1, "x", &["a"])));
+        assert!(!q.matches(&entry(1, "x", &["b"])));
+        assert!(q.matches(&entry(1, "x", &["b", "c"])));
+
+        let q = query(r#""team meeting" OR NOT urgent"#).unwrap();
+        assert!(q.matches(&entry(1, "Weekly team meeting", &["urgent"])));
+        assert!(q.matches(&entry(1, "Lunch", &[])));
+        assert!(!q.matches(&entry(1, "Lunch", &["urgent"])));
+
+        let q = query("on:2026-10-03").unwrap();
+        assert!(q.matches(&entry(3, "x", &[])));
+        assert!(!q.matches(&entry(4, "x", &[])));
+    }
+
+    #[test]
+    fn test_query_tags_match_like_filters() {
+        let config = Config {
+            tag_aliases: [("wrk".to_string(), "work".to_string())].into_iter().collect(),
+            ..Config::default()
+        };
+        let now = Local.with_ymd_and_hms(2026, 10, 18, 12, 0, 0).unwrap();
+        let q = Query::parse("tag:wrk", now, &config).unwrap();
+        assert!(q.matches(&entry(1, "x", &["work/api"])));
+        assert!(!q.matches(&entry(1, "x", &["wrk"])));
+
+        // The same tags as `-t work` match, case included
+        let tags = vec!["work".to_string()];
+        for entry_tags in [&["work"][..], &["Work"], &["work/api"], &["workshop"]] {
+            let e = entry(1, "x", entry_tags);
+            assert_eq!(
+                query("tag:work").unwrap().matches(&e),
+                crate::util::should_include_log(&e, &None, &None, &tags, &None),
+                "{:?}",
+                entry_tags
+            );
+        }
+        assert!(!query("tag:work").unwrap().matches(&entry(1, "x", &["Work"])));
+    }
+
+    #[test]
+    fn test_query_errors() {
+        assert_eq!(query("").unwrap_err(), "Invalid query: the query is empty");
+        assert!(query("after:-1e300").unwrap_err().contains("Invalid time"));
+        assert_eq!(
+            query("(tag:a or tag:b").unwrap_err(),
+            "Invalid query: missing ')' to close '(' at position 1"
+        );
+        assert_eq!(
+            query("tag:a and").unwrap_err(),
+            "Invalid query: expected a term at position 10 but the query ended"
+        );
+        assert_eq!(
+            query("tag:a )").unwrap_err(),
+            "Invalid query: unexpected ')' at position 7"
+        );
+        assert_eq!(
+            query("colour:red").unwrap_err(),
+            "Invalid query: unknown field 'colour' at position 1 (expected tag, content, id, on, after or before)"
+        );
+        assert_eq!(
+            query(r#"content:"open"#).unwrap_err(),
+            "Invalid query: unterminated quote starting at position 9"
+        );
+        assert_eq!(
+            query("tag: work").unwrap_err(),
+            "Invalid query: missing value for 'tag:' at position 1"
+        );
+        assert!(query("after:someday").unwrap_err().contains("at position 1"));
+        assert_eq!(
+            query("or tag:a").unwrap_err(),
+            "Invalid query: expected a term but found 'or' at position 1"
+        );
+    }
+}
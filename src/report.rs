@@ -0,0 +1,397 @@
+use crate::config::Config;
+use crate::dates::start_of_week;
+use crate::log_entry::LogEntry;
+use crate::output::{self, OutputFormat};
+use crate::storage::{self, Scope};
+use crate::util::{format_duration, sort_tags, tag_at_depth, Filter};
+use chrono::{Local, NaiveDate, Weekday};
+use serde::{Serialize, Serializer};
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+
+/// How the time of an entry with several tags is attributed to them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Split {
+    /// Divided equally between the tags.
+    Even,
+    /// Counted in full for every tag, so tag totals can exceed the period.
+    Full,
+    /// Counted only for the first tag given.
+    First,
+}
+
+impl FromStr for Split {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "even" => Ok(Split::Even),
+            "full" => Ok(Split::Full),
+            "first" => Ok(Split::First),
+            other => Err(format!("Unknown split: {} (expected even, full or first)", other)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Breakdown {
+    Total,
+    Day,
+    Week,
+}
+
+impl FromStr for Breakdown {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "total" => Ok(Breakdown::Total),
+            "day" => Ok(Breakdown::Day),
+            "week" => Ok(Breakdown::Week),
+            other => Err(format!("Unknown breakdown: {} (expected total, day or week)", other)),
+        }
+    }
+}
+
+pub struct ReportOptions {
+    pub split: Split,
+    pub by: Breakdown,
+    pub combinations: bool,
+    /// Rolls hierarchical tags up to this many levels, so `work/clientA/api`
+    /// counts as `work` with a depth of 1.
+    pub depth: Option<usize>,
+}
+
+/// One line of a report. `group` is `total`, `tag` or `combination`;
+/// untagged time has empty `tags`. `period` is the first day of the day or
+/// week, or `None` for the whole report.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct ReportRow {
+    #[serde(serialize_with = "serialize_period")]
+    pub period: Option<NaiveDate>,
+    pub group: &'static str,
+    pub tags: Vec<String>,
+    pub seconds: i64,
+    pub percent: f64,
+}
+
+fn serialize_period<S: Serializer>(period: &Option<NaiveDate>, serializer: S) -> Result<S::Ok, S::Error> {
+    match period {
+        Some(day) => serializer.serialize_str(&day.format("%Y-%m-%d").to_string()),
+        None => serializer.serialize_none(),
+    }
+}
+
+pub fn report(
+    config: &Config,
+    filter: Filter,
+    scope: Scope,
+    options: ReportOptions,
+    format: OutputFormat,
+) {
+    let entries = {
+        let _lock = storage::lock_shared(config);
+        storage::read_scope_timed(storage::open(config).as_ref(), scope, &filter, Local::now())
+    };
+    let timed: Vec<(&LogEntry, i64)> = entries
+        .iter()
+        .filter_map(|(_, entry, duration)| Some((entry, (*duration)?)))
+        .collect();
+
+    let rows = build_report(&timed, &options, config.week_start);
+
+    if format != OutputFormat::Table {
+        output::print_json(format, "report", "report_row", &rows);
+        return;
+    }
+
+    if timed.is_empty() {
+        println!("No logs found.");
+        return;
+    }
+
+    for (i, section) in rows.chunk_by(|_, next| next.group != "total").enumerate() {
+        let total = &section[0];
+        if i > 0 {
+            println!();
+        }
+        match (total.period, options.by) {
+            (None, Breakdown::Total) => {}
+            (None, _) => println!("== All ==\n"),
+            (Some(day), Breakdown::Week) => {
+                println!("== Week of {} ==\n", day.format(&config.date_format))
+            }
+            (Some(day), _) => println!(
+                "== {} ({}) ==\n",
+                day.format(&config.date_format),
+                day.format("%a")
+            ),
+        }
+
+        println!("{:<30} {:>10} {:>7}", "TAG", "TIME", "SHARE");
+        println!("{:<30} {:>10} {:>7}", "---", "----", "-----");
+        for (j, row) in section.iter().enumerate().skip(1) {
+            if row.group == "combination" && section[j - 1].group != "combination" {
+                println!("\n{:<30} {:>10} {:>7}", "COMBINATION", "TIME", "SHARE");
+                println!("{:<30} {:>10} {:>7}", "-----------", "----", "-----");
+            }
+            let label = if row.tags.is_empty() {
+                "(untagged)".to_string()
+            } else {
+                row.tags.join(" + ")
+            };
+            println!("{:<30} {:>10} {:>6.1}%", label, format_duration(row.seconds), row.percent);
+        }
+        println!("\n{:<30} {:>10}", "TOTAL", format_duration(total.seconds));
+    }
+
+    if options.split == Split::Full {
+        println!("\nEntries with several tags count fully toward each, so shares can add up to more than 100%.");
+    }
+}
+
+/// Sums `timed` entries per tag (and tag combination) for each period, then
+/// for the whole report. Each period starts with its `total` row, followed by
+/// tags and combinations ordered by time.
+pub fn build_report(
+    timed: &[(&LogEntry, i64)],
+    options: &ReportOptions,
+    week_start: Weekday,
+) -> Vec<ReportRow> {
+    let mut periods: BTreeMap<NaiveDate, Vec<(&LogEntry, i64)>> = BTreeMap::new();
+    for &(entry, seconds) in timed {
+        let day = entry.timestamp.date_naive();
+        let key = match options.by {
+            Breakdown::Total => continue,
+            Breakdown::Day => day,
+            Breakdown::Week => start_of_week(day, week_start),
+        };
+        periods.entry(key).or_default().push((entry, seconds));
+    }
+
+    let mut rows = Vec::new();
+    for (period, items) in &periods {
+        rows.extend(summarize(Some(*period), items, options));
+    }
+    rows.extend(summarize(None, timed, options));
+    rows
+}
+
+fn summarize(
+    period: Option<NaiveDate>,
+    items: &[(&LogEntry, i64)],
+    options: &ReportOptions,
+) -> Vec<ReportRow> {
+    let total: i64 = items.iter().map(|(_, seconds)| seconds).sum();
+    let mut by_tag: HashMap<Vec<String>, i64> = HashMap::new();
+    let mut by_combination: HashMap<Vec<String>, i64> = HashMap::new();
+
+    for &(entry, seconds) in items {
+        let mut tags: Vec<String> = Vec::new();
+        for tag in &entry.tags {
+            let tag = match options.depth {
+                Some(depth) => tag_at_depth(tag, depth),
+                None => tag,
+            };
+            if !tags.iter().any(|t| t == tag) {
+                tags.push(tag.to_string());
+            }
+        }
+
+        if tags.is_empty() {
+            *by_tag.entry(Vec::new()).or_insert(0) += seconds;
+            continue;
+        }
+        if tags.len() > 1 {
+            *by_combination.entry(sort_tags(tags.clone())).or_insert(0) += seconds;
+        }
+        let count = tags.len() as i64;
+        for (i, tag) in tags.iter().enumerate() {
+            let share = match options.split {
+                Split::Full => seconds,
+                Split::First if i == 0 => seconds,
+                Split::First => continue,
+                // Hand out the remainder one second at a time so shares add up
+                Split::Even => seconds / count + i64::from((i as i64) < seconds % count),
+            };
+            *by_tag.entry(vec![tag.clone()]).or_insert(0) += share;
+        }
+    }
+
+    let percent = |seconds: i64| {
+        if total == 0 {
+            0.0
+        } else {
+            (seconds as f64 * 1000.0 / total as f64).round() / 10.0
+        }
+    };
+    let sorted = |map: HashMap<Vec<String>, i64>| {
+        let mut groups: Vec<(Vec<String>, i64)> = map.into_iter().collect();
+        // Untagged time goes last; otherwise by time, then by name
+        groups.sort_by(|a, b| {
+            a.0.is_empty()
+                .cmp(&b.0.is_empty())
+                .then(b.1.cmp(&a.1))
+                .then(a.0.cmp(&b.0))
+        });
+        groups
+    };
+
+    let mut rows = vec![ReportRow {
+        period,
+        group: "total",
+        tags: Vec::new(),
+        seconds: total,
+        percent: percent(total),
+    }];
+    for (tags, seconds) in sorted(by_tag) {
+        rows.push(ReportRow {
+            period,
+            group: "tag",
+            tags,
+            seconds,
+            percent: percent(seconds),
+        });
+    }
+    if options.combinations {
+        for (tags, seconds) in sorted(by_combination) {
+            rows.push(ReportRow {
+                period,
+                group: "combination",
+                tags,
+                seconds,
+                percent: percent(seconds),
+            });
+        }
+    }
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn entry(day: u32, tags: &[&str]) -> LogEntry {
+        LogEntry::new(
+            Local.with_ymd_and_hms(2026, 10, day, 9, 0, 0).unwrap(),
+            "Work".to_string(),
+            tags.iter().map(|t| t.to_string()).collect(),
+        )
+    }
+
+    fn summary(rows: &[ReportRow]) -> Vec<(Option<NaiveDate>, &str, String, i64)> {
+        rows.iter()
+            .map(|r| (r.period, r.group, r.tags.join("+"), r.seconds))
+            .collect()
+    }
+
+    #[test]
+    fn test_report_splits() {
+        let a = entry(12, &["dev", "client"]);
+        let b = entry(12, &["dev"]);
+        let c = entry(13, &[]);
+        let timed = vec![(&a, 3601), (&b, 600), (&c, 300)];
+        let options = |split| ReportOptions {
+            split,
+            by: Breakdown::Total,
+            combinations: true,
+            depth: None,
+        };
+
+        let rows = build_report(&timed, &options(Split::Even), Weekday::Mon);
+        assert_eq!(
+            summary(&rows),
+            vec![
+                (None, "total", String::new(), 4501),
+                (None, "tag", "dev".to_string(), 2401),
+                (None, "tag", "client".to_string(), 1800),
+                (None, "tag", String::new(), 300),
+                (None, "combination", "dev+client".to_string(), 3601),
+            ]
+        );
+        assert_eq!(rows[0].percent, 100.0);
+        assert_eq!(rows[3].percent, 6.7);
+
+        let rows = build_report(&timed, &options(Split::Full), Weekday::Mon);
+        assert_eq!(rows[1].seconds, 4201);
+        assert_eq!(rows[2].seconds, 3601);
+
+        let rows = build_report(&timed, &options(Split::First), Weekday::Mon);
+        assert_eq!(rows[1].tags, vec!["dev"]);
+        assert_eq!(rows[1].seconds, 4201);
+        assert!(!rows.iter().any(|r| r.group == "tag" && r.tags == vec!["client"]));
+    }
+
+    #[test]
+    fn test_report_breakdown() {
+        // Sunday the 11th and Monday the 12th fall in different weeks
+        let a = entry(11, &["dev"]);
+        let b = entry(12, &["dev"]);
+        let c = entry(13, &["ops"]);
+        let timed = vec![(&a, 60), (&b, 120), (&c, 180)];
+        let d = |day| NaiveDate::from_ymd_opt(2026, 10, day);
+        let options = ReportOptions {
+            split: Split::Even,
+            by: Breakdown::Week,
+            combinations: false,
+            depth: None,
+        };
+
+        let rows = build_report(&timed, &options, Weekday::Mon);
+        assert_eq!(
+            summary(&rows),
+            vec![
+                (d(5), "total", String::new(), 60),
+                (d(5), "tag", "dev".to_string(), 60),
+                (d(12), "total", String::new(), 300),
+                (d(12), "tag", "ops".to_string(), 180),
+                (d(12), "tag", "dev".to_string(), 120),
+                (None, "total", String::new(), 360),
+                (None, "tag", "dev".to_string(), 180),
+                (None, "tag", "ops".to_string(), 180),
+            ]
+        );
+
+        let by_day = build_report(
+            &timed,
+            &ReportOptions {
+                by: Breakdown::Day,
+                ..options
+            },
+            Weekday::Mon,
+        );
+        assert_eq!(by_day.iter().filter(|r| r.group == "total").count(), 4);
+    }
+
+    #[test]
+    fn test_report_depth() {
+        let a = entry(12, &["work/clientA/api", "work/clientA/ui"]);
+        let b = entry(12, &["work/clientB"]);
+        let c = entry(13, &["home"]);
+        let timed = vec![(&a, 600), (&b, 300), (&c, 100)];
+        let options = |depth| ReportOptions {
+            split: Split::Even,
+            by: Breakdown::Total,
+            combinations: true,
+            depth,
+        };
+
+        let rows = build_report(&timed, &options(Some(1)), Weekday::Mon);
+        assert_eq!(
+            summary(&rows),
+            vec![
+                (None, "total", String::new(), 1000),
+                (None, "tag", "work".to_string(), 900),
+                (None, "tag", "home".to_string(), 100),
+            ]
+        );
+
+        let rows = build_report(&timed, &options(Some(2)), Weekday::Mon);
+        assert_eq!(rows[1].tags, vec!["work/clientA"]);
+        assert_eq!(rows[1].seconds, 600);
+
+        let rows = build_report(&timed, &options(None), Weekday::Mon);
+        assert!(rows.iter().any(|r| r.group == "combination"));
+    }
+}
@@ -0,0 +1,1419 @@
+use crate::config::{ArchiveLayout, Backend, Config};
+use crate::log_entry::LogEntry;
+use crate::util::{entry_durations, Filter};
+use chrono::{DateTime, Local, Months, NaiveDate, SecondsFormat, TimeZone, Utc};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use fs2::FileExt;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
+use std::collections::HashSet;
+use std::hash::{BuildHasher, Hasher};
+use std::ffi::OsString;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// The collections of entries a backend keeps apart.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Store {
+    Active,
+    Archive,
+    Trash,
+}
+
+/// Prefix marking IDs of archived entries, e.g. `a:k3x9ab01`.
+pub const ARCHIVE_ID_PREFIX: &str = "a:";
+
+impl Store {
+    /// The ID as shown to users, marked with the store it lives in.
+    pub fn display_id(self, id: &str) -> String {
+        match self {
+            Store::Archive => format!("{}{}", ARCHIVE_ID_PREFIX, id),
+            _ => id.to_string(),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Store::Active => "active",
+            Store::Archive => "archive",
+            Store::Trash => "trash",
+        }
+    }
+}
+
+/// Which stores a read command looks at.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scope {
+    Active,
+    Archive,
+    All,
+}
+
+impl Scope {
+    /// The stores in the scope, oldest first.
+    pub fn stores(self) -> &'static [Store] {
+        match self {
+            Scope::Active => &[Store::Active],
+            Scope::Archive => &[Store::Archive],
+            Scope::All => &[Store::Archive, Store::Active],
+        }
+    }
+}
+
+/// Reads every store in `scope` as one chronological sequence, pairing each
+/// entry with the store it lives in.
+pub fn read_scope(storage: &dyn Storage, scope: Scope, filter: &Filter) -> Vec<(Store, LogEntry)> {
+    let stores = scope.stores();
+    let mut entries: Vec<(Store, LogEntry)> = stores
+        .iter()
+        .flat_map(|&store| storage.read(store, filter).into_iter().map(move |e| (store, e)))
+        .collect();
+    if stores.len() > 1 {
+        // Stable, so archived entries stay first on equal timestamps
+        entries.sort_by_key(|(_, entry)| entry.timestamp);
+    }
+    entries
+}
+
+/// [`read_scope`] with the time attributed to each entry (see
+/// [`entry_durations`]). Gaps are measured on the whole scope before
+/// filtering, so hiding an entry doesn't hand its time to the next one.
+pub fn read_scope_timed(
+    storage: &dyn Storage,
+    scope: Scope,
+    filter: &Filter,
+    now: DateTime<Local>,
+) -> Vec<(Store, LogEntry, Option<i64>)> {
+    let entries = read_scope(storage, scope, &Filter::default());
+    let durations = entry_durations(entries.iter().map(|(_, e)| e), now);
+    entries
+        .into_iter()
+        .zip(durations)
+        .filter(|((_, entry), _)| filter.matches(entry))
+        .map(|((store, entry), duration)| (store, entry, duration))
+        .collect()
+}
+
+/// Everything commands need to read and change the log. Entries are always
+/// returned in chronological order.
+pub trait Storage {
+    fn read(&self, store: Store, filter: &Filter) -> Vec<LogEntry>;
+    /// Adds entries that are newer than everything already stored.
+    fn append(&self, store: Store, entries: &[LogEntry]);
+    /// Adds entries at their chronological position, e.g. backdated ones.
+    fn insert(&self, store: Store, entries: &[LogEntry]);
+    /// Replaces the entry with the same ID, moving it if its timestamp
+    /// changed. Returns false if there is none.
+    fn update(&self, store: Store, entry: &LogEntry) -> bool {
+        self.update_all(store, std::slice::from_ref(entry)) > 0
+    }
+    /// Replaces every entry with the same ID as one of `entries` in a single
+    /// write where possible, and returns how many it found.
+    fn update_all(&self, store: Store, entries: &[LogEntry]) -> usize;
+    /// Removes the entries with the given IDs and returns them.
+    fn delete(&self, store: Store, ids: &[String]) -> Vec<LogEntry>;
+    /// Moves the entries with the given IDs from one store to another, at
+    /// their chronological position, and returns them.
+    fn move_entries(&self, from: Store, to: Store, ids: &[String]) -> Vec<LogEntry>;
+    /// Moves active entries dated before `cutoff` to the archive and returns them.
+    fn archive(&self, cutoff: NaiveDate) -> Vec<LogEntry> {
+        let ids: Vec<String> = self
+            .read(Store::Active, &Filter::default())
+            .into_iter()
+            .filter(|entry| entry.timestamp.date_naive() < cutoff)
+            .map(|entry| entry.id)
+            .collect();
+        if ids.is_empty() {
+            return Vec::new();
+        }
+        self.move_entries(Store::Active, Store::Archive, &ids)
+    }
+    /// Finishes any operation a crash interrupted. Only call this while
+    /// holding the exclusive [`lock`].
+    fn recover(&self) {}
+    /// The stored lines (or rows) of a store that can't be read as entries.
+    fn unreadable(&self, store: Store) -> Vec<String>;
+    /// Removes the unreadable lines of a store and returns them.
+    fn remove_unreadable(&self, store: Store) -> Vec<String>;
+    /// Files of a store that can't be read at all, with the reason.
+    fn damaged(&self, _store: Store) -> Vec<(PathBuf, String)> {
+        Vec::new()
+    }
+    /// Renames the damaged files of a store out of the way, so the store can
+    /// be written again, and returns their new paths.
+    fn set_aside_damaged(&self, _store: Store) -> Vec<PathBuf> {
+        Vec::new()
+    }
+    /// Applies `change` to every entry of a store where it is stored, and
+    /// returns how many entries it changed.
+    fn rewrite(&self, store: Store, change: &mut dyn FnMut(&mut LogEntry) -> bool) -> usize;
+    /// Copies every file holding the log to one with `.suffix` appended, and
+    /// returns the copies.
+    fn backup(&self, suffix: &str) -> Vec<PathBuf>;
+}
+
+/// Tells the user, once per store, that some of it was skipped while reading.
+fn warn_unreadable(store: Store, count: usize) {
+    static WARNED: Mutex<Vec<Store>> = Mutex::new(Vec::new());
+    if count == 0 {
+        return;
+    }
+    let mut warned = WARNED.lock().unwrap_or_else(|e| e.into_inner());
+    if !warned.contains(&store) {
+        warned.push(store);
+        eprintln!(
+            "Warning: skipped {} unreadable line(s) in the {} log. Run `acty doctor` for details.",
+            count,
+            store.name()
+        );
+    }
+}
+
+pub fn open(config: &Config) -> Box<dyn Storage> {
+    match config.backend {
+        Backend::Json => Box::new(JsonLinesStorage::new(config)),
+        Backend::Sqlite => Box::new(SqliteStorage::open(&config.database_path())),
+    }
+}
+
+/// An advisory lock on the log, released when dropped.
+pub struct LogLock {
+    _file: File,
+}
+
+/// Takes the exclusive lock every mutating command must hold, waiting for
+/// other acty processes to finish first.
+pub fn lock(config: &Config) -> LogLock {
+    let lock = acquire_lock(config, true);
+    open(config).recover();
+    lock
+}
+
+/// Takes the exclusive lock without finishing an interrupted change first,
+/// for commands that must see the files exactly as they were left.
+pub fn lock_unrecovered(config: &Config) -> LogLock {
+    acquire_lock(config, true)
+}
+
+/// Takes a shared lock so readers never see a half finished change.
+pub fn lock_shared(config: &Config) -> LogLock {
+    acquire_lock(config, false)
+}
+
+fn acquire_lock(config: &Config, exclusive: bool) -> LogLock {
+    let path = companion_path(config, "lock");
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).expect("Unable to create log directory");
+    }
+
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&path)
+        .expect("Unable to open the lock file");
+    if exclusive {
+        file.lock_exclusive()
+    } else {
+        file.lock_shared()
+    }
+    .expect("Unable to lock the log file");
+
+    LogLock { _file: file }
+}
+
+/// A file kept next to the log (or the database) with `.suffix` appended
+/// to its name, such as the lock file.
+pub fn companion_path(config: &Config, suffix: &str) -> PathBuf {
+    match config.backend {
+        Backend::Json => sibling_path(Path::new(&config.log_file), suffix),
+        Backend::Sqlite => sibling_path(&config.database_path(), suffix),
+    }
+}
+
+/// `path` with `.suffix` appended to its full file name.
+fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut name: OsString = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Replaces `path` with `contents` without ever leaving a partially written
+/// file behind: the data goes to a temporary file that is renamed over it.
+/// The temporary file's name is unique, so concurrent writers can't rename
+/// each other's away.
+pub fn replace_file(path: &Path, contents: impl AsRef<[u8]>) {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).expect("Unable to create log directory");
+    }
+
+    let nonce = RandomState::new().build_hasher().finish();
+    let temp_path = sibling_path(path, &format!("{}-{:016x}.tmp", std::process::id(), nonce));
+    let mut file = File::create(&temp_path).expect("Unable to open log file for writing");
+    file.write_all(contents.as_ref())
+        .expect("Unable to write to log file");
+    file.sync_all().expect("Unable to write to log file");
+    std::fs::rename(&temp_path, path).expect("Unable to replace log file");
+}
+
+/// The original format: one JSON object per line in `log_file`, with archived
+/// entries in an `archive.json` and deleted ones in a `trash.json` next to it.
+/// With the monthly archive layout, archived entries instead go to one
+/// gzip-compressed file per month, e.g. `archive/2026-09.jsonl.gz`.
+pub struct JsonLinesStorage {
+    log_path: PathBuf,
+    archive_path: PathBuf,
+    trash_path: PathBuf,
+    /// Where the monthly archive partitions live, when that layout is used.
+    partition_dir: Option<PathBuf>,
+}
+
+// Lines that fail to parse are carried along untouched so rewriting the file
+// never loses data.
+enum Line {
+    Entry(LogEntry),
+    Raw(String),
+}
+
+// Entries moving between files are recorded before either file is touched.
+#[derive(Serialize, Deserialize)]
+struct PendingMove {
+    from: Store,
+    to: Store,
+    entries: Vec<LogEntry>,
+}
+
+const PARTITION_SUFFIX: &str = ".jsonl.gz";
+
+impl JsonLinesStorage {
+    pub fn new(config: &Config) -> Self {
+        let log_path = PathBuf::from(&config.log_file);
+        let archive_dir = match &config.archive_dir {
+            Some(dir) => PathBuf::from(dir),
+            None => log_path.parent().map(Path::to_path_buf).unwrap_or_default(),
+        };
+        let archive_path = archive_dir.join("archive.json");
+        let trash_path = log_path.with_file_name("trash.json");
+        let partition_dir = match config.archive_layout {
+            ArchiveLayout::Single => None,
+            ArchiveLayout::Monthly => Some(archive_dir.join("archive")),
+        };
+        JsonLinesStorage {
+            log_path,
+            archive_path,
+            trash_path,
+            partition_dir,
+        }
+    }
+
+    fn path(&self, store: Store) -> &Path {
+        match store {
+            Store::Active => &self.log_path,
+            Store::Archive => &self.archive_path,
+            Store::Trash => &self.trash_path,
+        }
+    }
+
+    /// The monthly partition of the given store, if it is partitioned.
+    fn partition_dir(&self, store: Store) -> Option<&Path> {
+        match store {
+            Store::Archive => self.partition_dir.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// The files holding a store's entries in chronological order, leaving out
+    /// partitions that can't contain entries matching `filter`. A monthly
+    /// archive still reads an `archive.json` that hasn't been migrated yet.
+    fn files(&self, store: Store, filter: &Filter) -> Vec<PathBuf> {
+        let dir = match self.partition_dir(store) {
+            Some(dir) => dir,
+            None => return vec![self.path(store).to_path_buf()],
+        };
+
+        let mut files = Vec::new();
+        if self.archive_path.exists() {
+            files.push(self.archive_path.clone());
+        }
+        let mut partitions: Vec<(NaiveDate, PathBuf)> = std::fs::read_dir(dir)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok().map(|e| e.path()))
+                    .filter_map(|path| partition_month(&path).map(|month| (month, path)))
+                    .collect()
+            })
+            .unwrap_or_default();
+        partitions.sort();
+        files.extend(
+            partitions
+                .into_iter()
+                .filter(|(month, _)| filter.overlaps(*month, last_day_of_month(*month)))
+                .map(|(_, path)| path),
+        );
+        files
+    }
+
+    /// The file a new entry of `store` belongs in.
+    fn file_for(&self, store: Store, entry: &LogEntry) -> PathBuf {
+        match self.partition_dir(store) {
+            Some(dir) => dir.join(format!(
+                "{}{}",
+                entry.timestamp.format("%Y-%m"),
+                PARTITION_SUFFIX
+            )),
+            None => self.path(store).to_path_buf(),
+        }
+    }
+
+    /// Reads every line of a file, giving entries written before IDs existed
+    /// a stable ID. Reads never write, since they may hold only the shared
+    /// lock; the IDs are persisted by the next change to the file, and are
+    /// the same on every read until then.
+    fn load(&self, path: &Path) -> Vec<Line> {
+        let contents = match read_file(path) {
+            Ok(Some(c)) => c,
+            Ok(None) => return Vec::new(),
+            Err(e) => {
+                mark_damaged(path, &e);
+                return Vec::new();
+            }
+        };
+
+        let mut seen: HashSet<String> = HashSet::new();
+        let mut lines: Vec<Line> = Vec::new();
+
+        for line in contents.lines() {
+            match serde_json::from_str::<LogEntry>(line) {
+                Ok(mut entry) => {
+                    if entry.id.is_empty() {
+                        let mut salt = 0;
+                        entry.assign_legacy_id(salt);
+                        while seen.contains(&entry.id) {
+                            salt += 1;
+                            entry.assign_legacy_id(salt);
+                        }
+                    }
+                    seen.insert(entry.id.clone());
+                    lines.push(Line::Entry(entry));
+                }
+                Err(_) => lines.push(Line::Raw(line.to_string())),
+            }
+        }
+
+        lines
+    }
+
+    fn save(&self, path: &Path, lines: &[Line]) {
+        if is_damaged(path) {
+            panic!(
+                "Unable to write to {}: the file is damaged. Run `acty doctor --fix` to set it aside.",
+                path.display()
+            );
+        }
+        // Emptied archive files of the monthly layout are removed rather than
+        // left behind to be opened by every read.
+        if lines.is_empty() && self.is_archive_file(path) {
+            if path.exists() {
+                std::fs::remove_file(path).expect("Unable to remove the archive file");
+            }
+            return;
+        }
+
+        let mut contents = String::new();
+        for line in lines {
+            match line {
+                Line::Entry(entry) => contents.push_str(
+                    &serde_json::to_string(entry).expect("Unable to serialize the log entry"),
+                ),
+                Line::Raw(raw) => contents.push_str(raw),
+            }
+            contents.push('\n');
+        }
+
+        if is_compressed(path) {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder
+                .write_all(contents.as_bytes())
+                .expect("Unable to compress the log file");
+            replace_file(path, encoder.finish().expect("Unable to compress the log file"));
+        } else {
+            replace_file(path, &contents);
+        }
+    }
+
+    fn is_archive_file(&self, path: &Path) -> bool {
+        match &self.partition_dir {
+            Some(dir) => path.starts_with(dir) || path == self.archive_path,
+            None => false,
+        }
+    }
+
+    /// Adds entries to the file they belong in at their chronological
+    /// position.
+    fn insert_into(&self, path: &Path, entries: &[LogEntry]) {
+        let mut lines = self.load(path);
+        for entry in entries {
+            Self::insert_sorted(&mut lines, entry.clone());
+        }
+        self.save(path, &lines);
+    }
+
+    /// Puts `entry` after the last entry that is not newer than it.
+    fn insert_sorted(lines: &mut Vec<Line>, entry: LogEntry) {
+        let position = lines
+            .iter()
+            .rposition(|line| matches!(line, Line::Entry(e) if e.timestamp <= entry.timestamp))
+            .map(|index| index + 1)
+            .unwrap_or(0);
+        lines.insert(position, Line::Entry(entry));
+    }
+
+    /// Entries being moved between stores (e.g. by an `archive` run) are
+    /// recorded here first, so an interrupted move can be completed by
+    /// [`Storage::recover`].
+    fn pending_move_path(&self) -> PathBuf {
+        sibling_path(&self.log_path, "moving")
+    }
+
+    /// Splits an `archive.json` into monthly partitions. Entries already in a
+    /// partition are skipped, so an interrupted run can simply be repeated.
+    /// Lines that can't be parsed stay in `archive.json`; the file is removed
+    /// once nothing is left in it. Returns how many entries were moved.
+    pub fn split_archive(&self) -> Result<usize, String> {
+        if self.partition_dir.is_none() {
+            return Err("set archive_layout = \"monthly\" in the config first".to_string());
+        }
+
+        let lines = self.load(&self.archive_path);
+        let mut present: HashSet<String> = HashSet::new();
+        for path in self.files(Store::Archive, &Filter::default()) {
+            if path != self.archive_path {
+                present.extend(self.load(&path).into_iter().filter_map(|line| match line {
+                    Line::Entry(entry) => Some(entry.id),
+                    Line::Raw(_) => None,
+                }));
+            }
+        }
+
+        let mut by_file: Vec<(PathBuf, Vec<LogEntry>)> = Vec::new();
+        let mut raw = Vec::new();
+        let mut count = 0;
+        for line in lines {
+            match line {
+                Line::Entry(entry) => {
+                    count += 1;
+                    if present.contains(&entry.id) {
+                        continue;
+                    }
+                    let path = self.file_for(Store::Archive, &entry);
+                    match by_file.iter_mut().find(|(p, _)| *p == path) {
+                        Some((_, entries)) => entries.push(entry),
+                        None => by_file.push((path, vec![entry])),
+                    }
+                }
+                other => raw.push(other),
+            }
+        }
+
+        for (path, entries) in &by_file {
+            self.insert_into(path, entries);
+        }
+        self.save(&self.archive_path, &raw);
+        Ok(count)
+    }
+}
+
+/// The first day of the month a partition file such as `2026-09.jsonl.gz`
+/// holds.
+fn partition_month(path: &Path) -> Option<NaiveDate> {
+    let name = path.file_name()?.to_str()?;
+    let month = name.strip_suffix(PARTITION_SUFFIX)?;
+    NaiveDate::parse_from_str(&format!("{}-01", month), "%Y-%m-%d").ok()
+}
+
+fn last_day_of_month(first: NaiveDate) -> NaiveDate {
+    first
+        .checked_add_months(Months::new(1))
+        .and_then(|next| next.pred_opt())
+        .expect("Date out of range")
+}
+
+fn is_compressed(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "gz")
+}
+
+/// The contents of a log file, `None` if it doesn't exist, or why it can't
+/// be read, e.g. a truncated compressed partition.
+fn read_file(path: &Path) -> Result<Option<String>, String> {
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.to_string()),
+    };
+    let mut contents = String::new();
+    let read = if is_compressed(path) {
+        GzDecoder::new(file).read_to_string(&mut contents)
+    } else {
+        file.read_to_string(&mut contents)
+    };
+    read.map(|_| Some(contents)).map_err(|e| e.to_string())
+}
+
+/// Files that couldn't be read. They are skipped by reads and never
+/// overwritten, so what is left of them can still be recovered.
+static DAMAGED: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+fn mark_damaged(path: &Path, error: &str) {
+    let mut damaged = DAMAGED.lock().unwrap_or_else(|e| e.into_inner());
+    if !damaged.iter().any(|p| p == path) {
+        damaged.push(path.to_path_buf());
+        eprintln!(
+            "Warning: skipped {}, which can't be read ({}). Run `acty doctor` for details.",
+            path.display(),
+            error
+        );
+    }
+}
+
+fn is_damaged(path: &Path) -> bool {
+    DAMAGED
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .any(|p| p == path)
+}
+
+impl Storage for JsonLinesStorage {
+    fn read(&self, store: Store, filter: &Filter) -> Vec<LogEntry> {
+        let files = self.files(store, filter);
+        let mut entries: Vec<LogEntry> = Vec::new();
+        let mut unreadable = 0;
+        for line in files.iter().flat_map(|path| self.load(path)) {
+            match line {
+                Line::Entry(entry) if filter.matches(&entry) => entries.push(entry),
+                Line::Raw(raw) if !raw.trim().is_empty() => unreadable += 1,
+                _ => {}
+            }
+        }
+        warn_unreadable(store, unreadable);
+        if files.len() > 1 {
+            // Partitions are already in order, but an unmigrated archive.json
+            // may overlap them
+            entries.sort_by_key(|entry| entry.timestamp);
+        }
+        entries
+    }
+
+    fn append(&self, store: Store, entries: &[LogEntry]) {
+        if self.partition_dir(store).is_some() {
+            // Compressed files can't be appended to
+            self.insert(store, entries);
+            return;
+        }
+
+        let path = self.path(store);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).expect("Unable to create log directory");
+        }
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path)
+            .expect("Unable to open or create the log file");
+
+        // Never glue a new entry onto a line left incomplete by a crash
+        if file.metadata().map(|m| m.len() > 0).unwrap_or(false) {
+            let mut last = [0u8; 1];
+            file.seek(SeekFrom::End(-1))
+                .and_then(|_| file.read_exact(&mut last))
+                .expect("Unable to read the log file");
+            if last[0] != b'\n' {
+                writeln!(file).expect("Unable to write to the log file");
+            }
+        }
+
+        for entry in entries {
+            let serialized =
+                serde_json::to_string(entry).expect("Unable to serialize the log entry");
+            writeln!(file, "{}", serialized).expect("Unable to write to the log file");
+        }
+    }
+
+    fn insert(&self, store: Store, entries: &[LogEntry]) {
+        let mut by_file: Vec<(PathBuf, Vec<LogEntry>)> = Vec::new();
+        for entry in entries {
+            let path = self.file_for(store, entry);
+            match by_file.iter_mut().find(|(p, _)| *p == path) {
+                Some((_, group)) => group.push(entry.clone()),
+                None => by_file.push((path, vec![entry.clone()])),
+            }
+        }
+        for (path, group) in &by_file {
+            self.insert_into(path, group);
+        }
+    }
+
+    fn update_all(&self, store: Store, entries: &[LogEntry]) -> usize {
+        let mut found = 0;
+        // Entries whose new timestamp belongs in another month's partition
+        let mut elsewhere = Vec::new();
+        for path in self.files(store, &Filter::default()) {
+            let mut lines = self.load(&path);
+            let mut moved = Vec::new();
+            let mut changed = false;
+            for entry in entries {
+                let position = lines
+                    .iter()
+                    .position(|line| matches!(line, Line::Entry(e) if e.id == entry.id));
+                let index = match position {
+                    Some(index) => index,
+                    None => continue,
+                };
+                found += 1;
+                changed = true;
+
+                if matches!(&lines[index], Line::Entry(e) if e.timestamp == entry.timestamp) {
+                    lines[index] = Line::Entry(entry.clone());
+                } else {
+                    lines.remove(index);
+                    if self.file_for(store, entry) == path {
+                        moved.push(entry.clone());
+                    } else {
+                        elsewhere.push(entry.clone());
+                    }
+                }
+            }
+            for entry in moved {
+                Self::insert_sorted(&mut lines, entry);
+            }
+            if changed {
+                self.save(&path, &lines);
+            }
+        }
+        self.insert(store, &elsewhere);
+        found
+    }
+
+    fn delete(&self, store: Store, ids: &[String]) -> Vec<LogEntry> {
+        let mut deleted = Vec::new();
+        for path in self.files(store, &Filter::default()) {
+            let mut kept = Vec::new();
+            let before = deleted.len();
+            for line in self.load(&path) {
+                match line {
+                    Line::Entry(entry) if ids.contains(&entry.id) => deleted.push(entry),
+                    other => kept.push(other),
+                }
+            }
+
+            if deleted.len() > before {
+                self.save(&path, &kept);
+            }
+        }
+        deleted
+    }
+
+    fn move_entries(&self, from: Store, to: Store, ids: &[String]) -> Vec<LogEntry> {
+        let moved: Vec<LogEntry> = self
+            .read(from, &Filter::default())
+            .into_iter()
+            .filter(|entry| ids.contains(&entry.id))
+            .collect();
+
+        if moved.is_empty() {
+            return moved;
+        }
+
+        let pending_path = self.pending_move_path();
+        let pending = PendingMove {
+            from,
+            to,
+            entries: moved,
+        };
+        replace_file(
+            &pending_path,
+            serde_json::to_string(&pending).expect("Unable to serialize the log entry"),
+        );
+
+        self.insert(to, &pending.entries);
+        self.delete(from, ids);
+        std::fs::remove_file(&pending_path).expect("Unable to finish moving entries");
+        pending.entries
+    }
+
+    fn recover(&self) {
+        let pending_path = self.pending_move_path();
+        let pending: PendingMove = match std::fs::read_to_string(&pending_path)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+        {
+            Some(p) => p,
+            None => return,
+        };
+        let pending_ids: Vec<String> = pending.entries.iter().map(|e| e.id.clone()).collect();
+
+        let present: HashSet<String> = self
+            .read(pending.to, &Filter::default())
+            .into_iter()
+            .map(|e| e.id)
+            .collect();
+        let missing: Vec<LogEntry> = pending
+            .entries
+            .into_iter()
+            .filter(|e| !present.contains(&e.id))
+            .collect();
+        if !missing.is_empty() {
+            self.insert(pending.to, &missing);
+        }
+        self.delete(pending.from, &pending_ids);
+
+        std::fs::remove_file(&pending_path).expect("Unable to finish moving entries");
+        eprintln!(
+            "Completed an interrupted move of {} entry(ies) to the {} log.",
+            pending_ids.len(),
+            pending.to.name()
+        );
+    }
+
+    fn unreadable(&self, store: Store) -> Vec<String> {
+        self.files(store, &Filter::default())
+            .iter()
+            .flat_map(|path| self.load(path))
+            .filter_map(|line| match line {
+                Line::Raw(raw) if !raw.trim().is_empty() => Some(raw),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn damaged(&self, store: Store) -> Vec<(PathBuf, String)> {
+        self.files(store, &Filter::default())
+            .into_iter()
+            .filter_map(|path| match read_file(&path) {
+                Err(e) => Some((path, e)),
+                Ok(_) => None,
+            })
+            .collect()
+    }
+
+    fn set_aside_damaged(&self, store: Store) -> Vec<PathBuf> {
+        let mut moved = Vec::new();
+        for (path, _) in self.damaged(store) {
+            let aside = sibling_path(&path, "damaged");
+            std::fs::rename(&path, &aside).expect("Unable to move the damaged file");
+            DAMAGED
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .retain(|p| *p != path);
+            moved.push(aside);
+        }
+        moved
+    }
+
+    fn remove_unreadable(&self, store: Store) -> Vec<String> {
+        let mut removed = Vec::new();
+        for path in self.files(store, &Filter::default()) {
+            let (entries, raw): (Vec<Line>, Vec<Line>) = self
+                .load(&path)
+                .into_iter()
+                .partition(|line| matches!(line, Line::Entry(_)));
+            if raw.is_empty() {
+                continue;
+            }
+            removed.extend(raw.into_iter().filter_map(|line| match line {
+                Line::Raw(raw) if !raw.trim().is_empty() => Some(raw),
+                _ => None,
+            }));
+            self.save(&path, &entries);
+        }
+        removed
+    }
+
+    fn rewrite(&self, store: Store, change: &mut dyn FnMut(&mut LogEntry) -> bool) -> usize {
+        let mut changed = 0;
+        for path in self.files(store, &Filter::default()) {
+            let mut lines = self.load(&path);
+            let before = changed;
+            for line in &mut lines {
+                if let Line::Entry(entry) = line {
+                    if change(entry) {
+                        changed += 1;
+                    }
+                }
+            }
+            if changed > before {
+                self.save(&path, &lines);
+            }
+        }
+        changed
+    }
+
+    fn backup(&self, suffix: &str) -> Vec<PathBuf> {
+        let mut copies = Vec::new();
+        for store in [Store::Active, Store::Archive, Store::Trash] {
+            for path in self.files(store, &Filter::default()) {
+                if path.exists() {
+                    let copy = sibling_path(&path, suffix);
+                    std::fs::copy(&path, &copy).expect("Unable to back up the log file");
+                    copies.push(copy);
+                }
+            }
+        }
+        copies
+    }
+}
+
+/// Keeps every entry as a row of a single table, so edits and deletes touch
+/// only the affected rows instead of rewriting the whole log.
+pub struct SqliteStorage {
+    conn: Connection,
+    path: PathBuf,
+}
+
+impl SqliteStorage {
+    pub fn open(path: &Path) -> Self {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).expect("Unable to create log directory");
+        }
+
+        let conn = Connection::open(path).expect("Unable to open the log database");
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS entries (
+                seq INTEGER PRIMARY KEY AUTOINCREMENT,
+                id TEXT NOT NULL UNIQUE,
+                store TEXT NOT NULL,
+                timestamp TEXT NOT NULL,
+                data TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS entries_store_timestamp ON entries (store, timestamp);",
+        )
+        .expect("Unable to initialize the log database");
+
+        SqliteStorage {
+            conn,
+            path: path.to_path_buf(),
+        }
+    }
+
+    fn read_rows(&self, store: Store) -> Vec<LogEntry> {
+        self.read_rows_between(store, (None, None))
+    }
+
+    /// The entries on the days between `bounds` (inclusive), found through
+    /// the timestamp index.
+    fn read_rows_between(&self, store: Store, bounds: (Option<NaiveDate>, Option<NaiveDate>)) -> Vec<LogEntry> {
+        let mut entries = Vec::new();
+        let mut unreadable = 0;
+        for (_, data) in self.raw_rows_between(store, bounds) {
+            match serde_json::from_str::<LogEntry>(&data) {
+                Ok(entry) => entries.push(entry),
+                Err(_) => unreadable += 1,
+            }
+        }
+        warn_unreadable(store, unreadable);
+        entries
+    }
+
+    fn raw_rows(&self, store: Store) -> Vec<(i64, String)> {
+        self.raw_rows_between(store, (None, None))
+    }
+
+    fn raw_rows_between(&self, store: Store, bounds: (Option<NaiveDate>, Option<NaiveDate>)) -> Vec<(i64, String)> {
+        let mut sql = "SELECT seq, data FROM entries WHERE store = ?".to_string();
+        let mut values = vec![store.name().to_string()];
+        if let Some(start) = bounds.0.and_then(start_of_day) {
+            sql.push_str(" AND timestamp >= ?");
+            values.push(start);
+        }
+        if let Some(end) = bounds.1.and_then(|day| day.succ_opt()).and_then(start_of_day) {
+            sql.push_str(" AND timestamp < ?");
+            values.push(end);
+        }
+        sql.push_str(" ORDER BY timestamp, seq");
+
+        let mut stmt = self.conn.prepare(&sql).expect("Unable to query the log database");
+        let rows = stmt
+            .query_map(rusqlite::params_from_iter(values), |row| Ok((row.get(0)?, row.get(1)?)))
+            .expect("Unable to query the log database");
+        rows.filter_map(|row| row.ok()).collect()
+    }
+
+    fn unreadable_rows(&self, store: Store) -> Vec<(i64, String)> {
+        self.raw_rows(store)
+            .into_iter()
+            .filter(|(_, data)| serde_json::from_str::<LogEntry>(data).is_err())
+            .collect()
+    }
+}
+
+// Timestamps are stored in UTC so the column sorts chronologically.
+fn sortable_timestamp(entry: &LogEntry) -> String {
+    entry
+        .timestamp
+        .with_timezone(&Utc)
+        .to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+/// Local midnight at the start of `day` in the form of the timestamp column,
+/// or `None` if it doesn't exist in the local time zone.
+fn start_of_day(day: NaiveDate) -> Option<String> {
+    Local
+        .from_local_datetime(&day.and_hms_opt(0, 0, 0)?)
+        .earliest()
+        .map(|start| start.with_timezone(&Utc).to_rfc3339_opts(SecondsFormat::Micros, true))
+}
+
+impl Storage for SqliteStorage {
+    fn read(&self, store: Store, filter: &Filter) -> Vec<LogEntry> {
+        self.read_rows_between(store, filter.date_bounds())
+            .into_iter()
+            .filter(|entry| filter.matches(entry))
+            .collect()
+    }
+
+    fn append(&self, store: Store, entries: &[LogEntry]) {
+        let tx = self
+            .conn
+            .unchecked_transaction()
+            .expect("Unable to write to the log database");
+        for entry in entries {
+            let data = serde_json::to_string(entry).expect("Unable to serialize the log entry");
+            tx.execute(
+                "INSERT INTO entries (id, store, timestamp, data) VALUES (?1, ?2, ?3, ?4)",
+                params![entry.id, store.name(), sortable_timestamp(entry), data],
+            )
+            .expect("Unable to write to the log database");
+        }
+        tx.commit().expect("Unable to write to the log database");
+    }
+
+    fn insert(&self, store: Store, entries: &[LogEntry]) {
+        // Rows are read back ordered by timestamp, so position does not matter
+        self.append(store, entries);
+    }
+
+    fn update_all(&self, store: Store, entries: &[LogEntry]) -> usize {
+        let tx = self
+            .conn
+            .unchecked_transaction()
+            .expect("Unable to write to the log database");
+        let mut found = 0;
+        for entry in entries {
+            let data = serde_json::to_string(entry).expect("Unable to serialize the log entry");
+            found += tx
+                .execute(
+                    "UPDATE entries SET timestamp = ?1, data = ?2 WHERE id = ?3 AND store = ?4",
+                    params![sortable_timestamp(entry), data, entry.id, store.name()],
+                )
+                .expect("Unable to write to the log database");
+        }
+        tx.commit().expect("Unable to write to the log database");
+        found
+    }
+
+    fn delete(&self, store: Store, ids: &[String]) -> Vec<LogEntry> {
+        let deleted: Vec<LogEntry> = self
+            .read_rows(store)
+            .into_iter()
+            .filter(|entry| ids.contains(&entry.id))
+            .collect();
+
+        let tx = self
+            .conn
+            .unchecked_transaction()
+            .expect("Unable to write to the log database");
+        for entry in &deleted {
+            tx.execute(
+                "DELETE FROM entries WHERE id = ?1 AND store = ?2",
+                params![entry.id, store.name()],
+            )
+            .expect("Unable to write to the log database");
+        }
+        tx.commit().expect("Unable to write to the log database");
+        deleted
+    }
+
+    fn move_entries(&self, from: Store, to: Store, ids: &[String]) -> Vec<LogEntry> {
+        let moved: Vec<LogEntry> = self
+            .read_rows(from)
+            .into_iter()
+            .filter(|entry| ids.contains(&entry.id))
+            .collect();
+
+        let tx = self
+            .conn
+            .unchecked_transaction()
+            .expect("Unable to write to the log database");
+        for entry in &moved {
+            tx.execute(
+                "UPDATE entries SET store = ?1 WHERE id = ?2 AND store = ?3",
+                params![to.name(), entry.id, from.name()],
+            )
+            .expect("Unable to write to the log database");
+        }
+        tx.commit().expect("Unable to write to the log database");
+        moved
+    }
+
+    fn unreadable(&self, store: Store) -> Vec<String> {
+        self.unreadable_rows(store)
+            .into_iter()
+            .map(|(_, data)| data)
+            .collect()
+    }
+
+    fn remove_unreadable(&self, store: Store) -> Vec<String> {
+        let rows = self.unreadable_rows(store);
+        let tx = self
+            .conn
+            .unchecked_transaction()
+            .expect("Unable to write to the log database");
+        for (seq, _) in &rows {
+            tx.execute("DELETE FROM entries WHERE seq = ?1", params![seq])
+                .expect("Unable to write to the log database");
+        }
+        tx.commit().expect("Unable to write to the log database");
+        rows.into_iter().map(|(_, data)| data).collect()
+    }
+
+    fn rewrite(&self, store: Store, change: &mut dyn FnMut(&mut LogEntry) -> bool) -> usize {
+        let mut changed = Vec::new();
+        for mut entry in self.read_rows(store) {
+            if change(&mut entry) {
+                changed.push(entry);
+            }
+        }
+        // All at once, in one transaction
+        if !changed.is_empty() {
+            self.update_all(store, &changed);
+        }
+        changed.len()
+    }
+
+    fn backup(&self, suffix: &str) -> Vec<PathBuf> {
+        let copy = sibling_path(&self.path, suffix);
+        let _ = std::fs::remove_file(&copy);
+        self.conn
+            .execute("VACUUM INTO ?1", params![copy.to_string_lossy()])
+            .expect("Unable to back up the log database");
+        vec![copy]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dates::DateRange;
+    use chrono::{DateTime, Duration, Local};
+
+    fn exercise_backend(storage: &dyn Storage) {
+        let old = LogEntry::new(
+            Local::now() - Duration::days(10),
+            "Old".to_string(),
+            vec!["work".to_string()],
+        );
+        let mut recent = LogEntry::new(Local::now(), "Recent".to_string(), vec![]);
+        let other = LogEntry::new(Local::now(), "Other".to_string(), vec!["home".to_string()]);
+        storage.append(Store::Active, &[old, recent.clone(), other.clone()]);
+
+        let all = storage.read(Store::Active, &Filter::default());
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[1].content, "Recent");
+
+        let work = Filter {
+            tags: vec!["work".to_string()],
+            ..Filter::default()
+        };
+        assert_eq!(storage.read(Store::Active, &work).len(), 1);
+
+        let today = Local::now().date_naive();
+        let around_old = Filter {
+            dates: Some(DateRange {
+                from: Some(today - Duration::days(11)),
+                to: Some(today - Duration::days(9)),
+            }),
+            ..Filter::default()
+        };
+        let read = storage.read(Store::Active, &around_old);
+        assert_eq!(read.len(), 1);
+        assert_eq!(read[0].content, "Old");
+        let last_days = Filter {
+            range: Some(2),
+            ..Filter::default()
+        };
+        assert_eq!(storage.read(Store::Active, &last_days).len(), 2);
+
+        recent.content = "Recent (edited)".to_string();
+        assert!(storage.update(Store::Active, &recent));
+        assert!(!storage.update(Store::Archive, &recent));
+
+        let deleted = storage.delete(Store::Active, std::slice::from_ref(&other.id));
+        assert_eq!(deleted.len(), 1);
+        assert_eq!(deleted[0].content, "Other");
+
+        let moved = storage.archive(Local::now().date_naive() - Duration::days(7));
+        assert_eq!(moved.len(), 1);
+        assert_eq!(moved[0].content, "Old");
+
+        // Moving back puts the entry at its chronological position
+        let back = storage.move_entries(Store::Archive, Store::Active, std::slice::from_ref(&moved[0].id));
+        assert_eq!(back.len(), 1);
+        assert_eq!(storage.read(Store::Active, &Filter::default())[0].content, "Old");
+        assert!(storage.read(Store::Archive, &Filter::default()).is_empty());
+        storage.move_entries(Store::Active, Store::Archive, std::slice::from_ref(&moved[0].id));
+
+        let active = storage.read(Store::Active, &Filter::default());
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].content, "Recent (edited)");
+        assert_eq!(storage.read(Store::Archive, &Filter::default()).len(), 1);
+
+        let backdated = LogEntry::new(Local::now() - Duration::hours(1), "Backdated".to_string(), vec![]);
+        storage.insert(Store::Active, std::slice::from_ref(&backdated));
+        let contents: Vec<String> = storage
+            .read(Store::Active, &Filter::default())
+            .into_iter()
+            .map(|e| e.content)
+            .collect();
+        assert_eq!(contents, vec!["Backdated", "Recent (edited)"]);
+
+        recent.timestamp = Local::now() - Duration::hours(2);
+        storage.update(Store::Active, &recent);
+        let contents: Vec<String> = storage
+            .read(Store::Active, &Filter::default())
+            .into_iter()
+            .map(|e| e.content)
+            .collect();
+        assert_eq!(contents, vec!["Recent (edited)", "Backdated"]);
+    }
+
+    #[test]
+    fn test_json_lines_backend() {
+        let test_dir = std::env::temp_dir().join("acty_test_storage_json");
+        let _ = std::fs::remove_dir_all(&test_dir);
+        let config = Config {
+            log_file: test_dir.join("action_log.json").to_string_lossy().to_string(),
+            ..Config::default()
+        };
+
+        exercise_backend(&JsonLinesStorage::new(&config));
+
+        std::fs::remove_dir_all(test_dir).unwrap();
+    }
+
+    #[test]
+    fn test_monthly_archive_partitions() {
+        let test_dir = std::env::temp_dir().join("acty_test_storage_monthly");
+        let _ = std::fs::remove_dir_all(&test_dir);
+        let config = Config {
+            log_file: test_dir.join("action_log.json").to_string_lossy().to_string(),
+            archive_layout: ArchiveLayout::Monthly,
+            ..Config::default()
+        };
+        let storage = JsonLinesStorage::new(&config);
+        exercise_backend(&storage);
+        std::fs::remove_dir_all(&test_dir).unwrap();
+
+        // An archive.json written before switching layouts is split by month
+        let at = |date: &str| {
+            DateTime::parse_from_rfc3339(&format!("{}T10:00:00+00:00", date))
+                .unwrap()
+                .with_timezone(&Local)
+        };
+        let july = LogEntry::new(at("2026-07-03"), "July".to_string(), vec![]);
+        let august = LogEntry::new(at("2026-08-03"), "August".to_string(), vec![]);
+        let single = JsonLinesStorage::new(&Config {
+            log_file: config.log_file.clone(),
+            ..Config::default()
+        });
+        single.append(Store::Archive, &[july.clone(), august.clone()]);
+        {
+            let mut file = OpenOptions::new().append(true).open(&storage.archive_path).unwrap();
+            writeln!(file, "not json").unwrap();
+        }
+
+        // Not yet migrated entries are still read
+        assert_eq!(storage.read(Store::Archive, &Filter::default()).len(), 2);
+        assert_eq!(storage.split_archive(), Ok(2));
+        assert_eq!(std::fs::read_to_string(&storage.archive_path).unwrap(), "not json\n");
+        assert!(test_dir.join("archive").join("2026-07.jsonl.gz").exists());
+        assert!(test_dir.join("archive").join("2026-08.jsonl.gz").exists());
+
+        // Only the partitions overlapping the dates are opened
+        let in_august = Filter {
+            dates: Some(DateRange {
+                from: NaiveDate::from_ymd_opt(2026, 8, 1),
+                to: NaiveDate::from_ymd_opt(2026, 8, 31),
+            }),
+            ..Filter::default()
+        };
+        let files = storage.files(Store::Archive, &in_august);
+        assert_eq!(files.len(), 2);
+        assert!(files[1].ends_with("2026-08.jsonl.gz"));
+        let read = storage.read(Store::Archive, &in_august);
+        assert_eq!(read.len(), 1);
+        assert_eq!(read[0].id, august.id);
+
+        // Moving the last entry out of a partition removes the file
+        storage.move_entries(Store::Archive, Store::Active, &[july.id]);
+        assert!(!test_dir.join("archive").join("2026-07.jsonl.gz").exists());
+
+        std::fs::remove_dir_all(test_dir).unwrap();
+    }
+
+    #[test]
+    fn test_read_scope_timed() {
+        let test_dir = std::env::temp_dir().join("acty_test_storage_timed");
+        let _ = std::fs::remove_dir_all(&test_dir);
+        let storage = SqliteStorage::open(&test_dir.join("action_log.db"));
+        let now = Local::now();
+        let at = |minutes| now - Duration::minutes(minutes);
+        storage.append(
+            Store::Active,
+            &[
+                LogEntry::new(at(60), "Start".to_string(), vec!["work".to_string()]),
+                LogEntry::new(at(40), "Coffee".to_string(), vec!["break".to_string()]),
+                LogEntry::new(at(10), "Deploy".to_string(), vec!["work".to_string()]),
+            ],
+        );
+
+        // Deploy's gap runs from Coffee, even when Coffee is filtered out
+        let work = Filter {
+            tags: vec!["work".to_string()],
+            ..Filter::default()
+        };
+        let timed = read_scope_timed(&storage, Scope::Active, &work, now);
+        let durations: Vec<Option<i64>> = timed.iter().map(|(_, _, d)| *d).collect();
+        assert_eq!(durations, vec![None, Some(1800)]);
+
+        std::fs::remove_dir_all(test_dir).unwrap();
+    }
+
+    #[test]
+    fn test_sqlite_backend() {
+        let test_dir = std::env::temp_dir().join("acty_test_storage_sqlite");
+        let _ = std::fs::remove_dir_all(&test_dir);
+
+        let storage = SqliteStorage::open(&test_dir.join("action_log.db"));
+        exercise_backend(&storage);
+
+        // Date filters are answered from the timestamp index
+        let today = Local::now().date_naive();
+        let plan: String = storage
+            .conn
+            .query_row(
+                "EXPLAIN QUERY PLAN SELECT seq, data FROM entries WHERE store = ?1 AND timestamp >= ?2 ORDER BY timestamp, seq",
+                params!["active", start_of_day(today).unwrap()],
+                |row| row.get(3),
+            )
+            .unwrap();
+        assert!(plan.contains("entries_store_timestamp"), "{}", plan);
+
+        // rewrite changes every entry at once
+        let renamed = storage.rewrite(Store::Active, &mut |entry| {
+            entry.content.push('!');
+            true
+        });
+        assert_eq!(renamed, 2);
+        assert!(storage
+            .read(Store::Active, &Filter::default())
+            .iter()
+            .all(|e| e.content.ends_with('!')));
+
+        std::fs::remove_dir_all(test_dir).unwrap();
+    }
+
+    #[test]
+    fn test_json_lines_keeps_unparseable_lines() {
+        let test_dir = std::env::temp_dir().join("acty_test_storage_raw");
+        std::fs::create_dir_all(&test_dir).unwrap();
+        let log_path = test_dir.join("action_log.json");
+        let config = Config {
+            log_file: log_path.to_string_lossy().to_string(),
+            ..Config::default()
+        };
+        let storage = JsonLinesStorage::new(&config);
+
+        let entry = LogEntry::new(Local::now(), "Valid".to_string(), vec![]);
+        storage.append(Store::Active, std::slice::from_ref(&entry));
+        {
+            let mut file = OpenOptions::new().append(true).open(&log_path).unwrap();
+            writeln!(file, "not json").unwrap();
+        }
+
+        storage.delete(Store::Active, &[entry.id]);
+        let contents = std::fs::read_to_string(&log_path).unwrap();
+        assert_eq!(contents, "not json\n");
+
+        std::fs::remove_dir_all(test_dir).unwrap();
+    }
+
+    #[test]
+    fn test_interrupted_archive_is_completed() {
+        let test_dir = std::env::temp_dir().join("acty_test_storage_recover");
+        let _ = std::fs::remove_dir_all(&test_dir);
+        let config = Config {
+            log_file: test_dir.join("action_log.json").to_string_lossy().to_string(),
+            ..Config::default()
+        };
+        let storage = JsonLinesStorage::new(&config);
+
+        let old = LogEntry::new(Local::now() - Duration::days(10), "Old".to_string(), vec![]);
+        let new = LogEntry::new(Local::now(), "New".to_string(), vec![]);
+        storage.append(Store::Active, &[old.clone(), new]);
+
+        // Simulate a crash after the archive was written but before the
+        // active log was rewritten.
+        let pending = PendingMove {
+            from: Store::Active,
+            to: Store::Archive,
+            entries: vec![old.clone()],
+        };
+        replace_file(&storage.pending_move_path(), serde_json::to_string(&pending).unwrap());
+        storage.append(Store::Archive, std::slice::from_ref(&old));
+
+        drop(lock(&config));
+
+        let active = storage.read(Store::Active, &Filter::default());
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].content, "New");
+        let archive = storage.read(Store::Archive, &Filter::default());
+        assert_eq!(archive.len(), 1);
+        assert_eq!(archive[0].id, old.id);
+        assert!(!storage.pending_move_path().exists());
+
+        std::fs::remove_dir_all(test_dir).unwrap();
+    }
+
+    #[test]
+    fn test_append_after_truncated_line() {
+        let test_dir = std::env::temp_dir().join("acty_test_storage_truncated");
+        let _ = std::fs::remove_dir_all(&test_dir);
+        std::fs::create_dir_all(&test_dir).unwrap();
+        let log_path = test_dir.join("action_log.json");
+        std::fs::write(&log_path, "{\"timestamp\":").unwrap();
+
+        let config = Config {
+            log_file: log_path.to_string_lossy().to_string(),
+            ..Config::default()
+        };
+        let storage = JsonLinesStorage::new(&config);
+        let entry = LogEntry::new(Local::now(), "After crash".to_string(), vec![]);
+        storage.append(Store::Active, std::slice::from_ref(&entry));
+
+        let entries = storage.read(Store::Active, &Filter::default());
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].id, entry.id);
+
+        std::fs::remove_dir_all(test_dir).unwrap();
+    }
+}
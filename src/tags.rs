@@ -1,92 +1,369 @@
 use crate::config::Config;
+use crate::journal::{self, Change};
 use crate::log_entry::LogEntry;
-use std::collections::HashMap;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use crate::output::{self, OutputFormat};
+use crate::storage::{self, Scope};
+use crate::util::{
+    format_duration, sort_tags, tag_ancestors, tag_matches, Filter, TAG_SEPARATOR,
+};
+use chrono::Local;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
 
-pub fn list_tags(config: &Config, use_archive: bool) {
-    let tag_counts = get_tag_counts(config, use_archive);
+/// How often a tag is used and the time logged under it, counting the
+/// entries tagged with anything below it too.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct Totals {
+    count: usize,
+    seconds: i64,
+}
+
+#[derive(Serialize)]
+struct TagRecord<'a> {
+    tag: &'a str,
+    parent: Option<&'a str>,
+    count: usize,
+    seconds: i64,
+}
+
+/// Prints the tags as a tree, each level rolling up the entries below it,
+/// down to `depth` levels when given.
+pub fn list_tags(config: &Config, filter: Filter, scope: Scope, depth: Option<usize>, format: OutputFormat) {
+    let totals = get_tag_totals(config, &filter, scope);
+    let tree = tree_order(&totals, depth);
+
+    if format != OutputFormat::Table {
+        let records: Vec<TagRecord> = tree
+            .iter()
+            .map(|tag| TagRecord {
+                tag,
+                parent: parent(tag),
+                count: totals[*tag].count,
+                seconds: totals[*tag].seconds,
+            })
+            .collect();
+        output::print_json(format, "tags", "tag", &records);
+        return;
+    }
 
-    if tag_counts.is_empty() {
+    if tree.is_empty() {
         println!("No tags found.");
         return;
     }
 
-    // Sort by count (descending), then by name (ascending)
-    let mut sorted_tags: Vec<(&String, &usize)> = tag_counts.iter().collect();
-    sorted_tags.sort_by(|a, b| {
-        let count_cmp = b.1.cmp(a.1); // Descending count
-        if count_cmp == std::cmp::Ordering::Equal {
-            a.0.cmp(b.0) // Ascending name
-        } else {
-            count_cmp
+    println!("{:<30} {:>5} {:>10}", "TAG", "COUNT", "TIME");
+    println!("{:<30} {:>5} {:>10}", "---", "-----", "----");
+
+    for tag in tree {
+        let level = tag.matches(TAG_SEPARATOR).count();
+        let name = tag.rsplit(TAG_SEPARATOR).next().unwrap_or(tag);
+        println!(
+            "{:<30} {:>5} {:>10}",
+            format!("{}{}", "  ".repeat(level), name),
+            totals[tag].count,
+            format_duration(totals[tag].seconds)
+        );
+    }
+}
+
+fn parent(tag: &str) -> Option<&str> {
+    tag.rsplit_once(TAG_SEPARATOR).map(|(parent, _)| parent)
+}
+
+/// The tags in tree order: each one followed by its children, siblings by
+/// count (descending), then by name.
+fn tree_order(totals: &HashMap<String, Totals>, depth: Option<usize>) -> Vec<&str> {
+    let mut children: HashMap<Option<&str>, Vec<&str>> = HashMap::new();
+    for tag in totals.keys() {
+        if depth.is_some_and(|depth| tag.matches(TAG_SEPARATOR).count() >= depth) {
+            continue;
         }
-    });
+        children.entry(parent(tag)).or_default().push(tag);
+    }
+    for siblings in children.values_mut() {
+        siblings.sort_by(|a, b| totals[*b].count.cmp(&totals[*a].count).then(a.cmp(b)));
+    }
 
-    println!("{:<20} {}", "TAG", "COUNT");
-    println!("{:<20} {}", "---", "-----");
+    let mut order = Vec::new();
+    let mut pending: Vec<&str> = children.get(&None).cloned().unwrap_or_default();
+    pending.reverse();
+    while let Some(tag) = pending.pop() {
+        order.push(tag);
+        if let Some(below) = children.get(&Some(tag)) {
+            pending.extend(below.iter().rev());
+        }
+    }
+    order
+}
 
-    for (tag, count) in sorted_tags {
-        println!("{:<20} {}", tag, count);
+fn get_tag_totals(config: &Config, filter: &Filter, scope: Scope) -> HashMap<String, Totals> {
+    let entries = {
+        let _lock = storage::lock_shared(config);
+        storage::read_scope_timed(storage::open(config).as_ref(), scope, filter, Local::now())
+    };
+
+    let mut totals: HashMap<String, Totals> = HashMap::new();
+    for (_, entry, duration) in &entries {
+        // An entry counts once for each level, however many of its tags
+        // share it
+        let nodes: BTreeSet<&str> = entry.tags.iter().flat_map(|tag| tag_ancestors(tag)).collect();
+        for node in nodes {
+            let node_totals = totals.entry(node.to_string()).or_default();
+            node_totals.count += 1;
+            node_totals.seconds += duration.unwrap_or(0);
+        }
     }
+    totals
+}
+
+/// Renames `from` and the tags below it: `work` to `job` turns
+/// `work/clientA` into `job/clientA`.
+pub fn rename_tag(config: &Config, from: &str, to: &str, scope: Scope, dry_run: bool) {
+    let command = format!("tags rename {} {}", from, to);
+    replace_tags(config, &[from.to_string()], to, scope, dry_run, &command);
 }
 
-fn get_tag_counts(config: &Config, use_archive: bool) -> HashMap<String, usize> {
-    let path = if use_archive {
-        std::path::Path::new(&config.log_file).with_file_name("archive.json")
+/// Replaces each of `sources` (and the tags below them) with `into`.
+pub fn merge_tags(config: &Config, sources: &[String], into: &str, scope: Scope, dry_run: bool) {
+    let command = format!("tags merge {} --into {}", sources.join(" "), into);
+    replace_tags(config, sources, into, scope, dry_run, &command);
+}
+
+fn replace_tags(
+    config: &Config,
+    sources: &[String],
+    into: &str,
+    scope: Scope,
+    dry_run: bool,
+    command: &str,
+) {
+    let into = into.trim_end_matches(TAG_SEPARATOR);
+    if into.trim().is_empty() || into.contains(',') {
+        eprintln!("Invalid tag '{}'.", into);
+        return;
+    }
+    let sources: Vec<&str> = sources.iter().map(|s| s.trim_end_matches(TAG_SEPARATOR)).collect();
+    change_tags(config, &sources, scope, dry_run, command, &|tag| {
+        let source = sources.iter().find(|source| tag_matches(tag, source))?;
+        Some(format!("{}{}", into, &tag[source.len()..]))
+    });
+}
+
+/// Removes `tag` and the tags below it from every entry.
+pub fn delete_tag(config: &Config, tag: &str, scope: Scope, dry_run: bool) {
+    let tag = tag.trim_end_matches(TAG_SEPARATOR);
+    change_tags(config, &[tag], scope, dry_run, &format!("tags delete {}", tag), &|_| None);
+}
+
+/// Changes every tag matching one of `targets` with `replace`, which gives
+/// the new tag or `None` to drop it, and records the changes for `undo`.
+fn change_tags(
+    config: &Config,
+    targets: &[&str],
+    scope: Scope,
+    dry_run: bool,
+    command: &str,
+    replace: &dyn Fn(&str) -> Option<String>,
+) {
+    let _lock = if dry_run {
+        storage::lock_shared(config)
     } else {
-        std::path::PathBuf::from(&config.log_file)
+        storage::lock(config)
     };
-    
-    if !path.exists() {
-        return HashMap::new();
+    let storage = storage::open(config);
+
+    let mut changes: Vec<Change> = Vec::new();
+    for &store in scope.stores() {
+        if dry_run {
+            for entry in storage.read(store, &Filter::default()) {
+                if let Some(after) = retag(&entry, targets, replace) {
+                    changes.push(Change::Update {
+                        store,
+                        before: Box::new(entry),
+                        after: Box::new(after),
+                    });
+                }
+            }
+            continue;
+        }
+        storage.rewrite(store, &mut |entry| match retag(entry, targets, replace) {
+            Some(after) => {
+                changes.push(Change::Update {
+                    store,
+                    before: Box::new(std::mem::replace(entry, after.clone())),
+                    after: Box::new(after),
+                });
+                true
+            }
+            None => false,
+        });
     }
 
-    let file = File::open(path).expect("Unable to open the log file");
-    let reader = BufReader::new(file);
-    let mut tag_counts: HashMap<String, usize> = HashMap::new();
+    if changes.is_empty() {
+        println!("No logs are tagged {}.", targets.join(" or "));
+        return;
+    }
 
-    for line in reader.lines() {
-        if let Ok(l) = line {
-            if let Ok(entry) = serde_json::from_str::<LogEntry>(&l) {
-                for tag in entry.tags {
-                    *tag_counts.entry(tag).or_insert(0) += 1;
-                }
+    if dry_run {
+        for change in &changes {
+            if let Change::Update { store, before, after } = change {
+                println!(
+                    "{}\t{}\t{} -> {}\t{}",
+                    store.display_id(&before.id),
+                    before.timestamp.format(&config.time_format),
+                    sort_tags(before.tags.clone()).join(", "),
+                    sort_tags(after.tags.clone()).join(", "),
+                    before.content
+                );
             }
         }
+        println!("Dry run: the tags of {} log entry(ies) would change.", changes.len());
+        return;
     }
-    tag_counts
+
+    let count = changes.len();
+    journal::record(config, command, changes);
+    println!("Changed the tags of {} log entry(ies).", count);
+}
+
+/// `entry` with its tags replaced, or `None` if none of them match.
+fn retag(entry: &LogEntry, targets: &[&str], replace: &dyn Fn(&str) -> Option<String>) -> Option<LogEntry> {
+    if !entry.tags.iter().any(|tag| targets.iter().any(|target| tag_matches(tag, target))) {
+        return None;
+    }
+    let mut after = entry.clone();
+    after.tags.clear();
+    for tag in &entry.tags {
+        let tag = if targets.iter().any(|target| tag_matches(tag, target)) {
+            match replace(tag) {
+                Some(tag) => tag,
+                None => continue,
+            }
+        } else {
+            tag.clone()
+        };
+        if !after.tags.contains(&tag) {
+            after.tags.push(tag);
+        }
+    }
+    (after != *entry).then_some(after)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::logger::log_action;
+    use crate::storage::Store;
     use std::fs;
 
     #[test]
     fn test_tag_counting() {
-        let test_json_path = "action_log_tags_test.json";
+        let test_dir = std::env::temp_dir().join("acty_test_tags");
+        let _ = fs::remove_dir_all(&test_dir);
+        let test_json_path = test_dir.join("action_log.json");
         let config = Config {
-            log_file: test_json_path.to_string(),
+            log_file: test_json_path.to_string_lossy().to_string(),
+            ..Config::default()
         };
 
-        if std::path::Path::new(test_json_path).exists() {
-            fs::remove_file(test_json_path).unwrap();
-        }
+        log_action(&config, "Log 1".to_string(), vec!["work".to_string(), "urgent".to_string()], None, None, None);
+        log_action(&config, "Log 2".to_string(), vec!["work".to_string(), "meeting".to_string()], None, None, None);
+        log_action(&config, "Log 3".to_string(), vec!["rest".to_string()], None, None, None);
+
+        let counts = get_tag_totals(&config, &Filter::default(), Scope::Active);
+
+        assert_eq!(counts.get("work").unwrap().count, 2);
+        assert_eq!(counts.get("urgent").unwrap().count, 1);
+        assert_eq!(counts.get("meeting").unwrap().count, 1);
+        assert_eq!(counts.get("rest").unwrap().count, 1);
+        assert!(!counts.contains_key("unknown"));
+
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+
+    #[test]
+    fn test_tag_tree() {
+        let test_dir = std::env::temp_dir().join("acty_test_tag_tree");
+        let _ = fs::remove_dir_all(&test_dir);
+        let config = Config {
+            log_file: test_dir.join("action_log.json").to_string_lossy().to_string(),
+            ..Config::default()
+        };
+        let at = |minutes| Some(Local::now() - chrono::Duration::minutes(minutes));
+
+        log_action(&config, "Start".to_string(), vec![], None, at(60), None);
+        log_action(&config, "API".to_string(), vec!["work/clientA/backend".to_string()], None, at(50), None);
+        log_action(&config, "UI".to_string(), vec!["work/clientA/frontend".to_string(), "work/clientA/backend".to_string()], None, at(30), None);
+        log_action(&config, "Call".to_string(), vec!["work/clientB".to_string()], None, at(20), None);
+
+        let totals = get_tag_totals(&config, &Filter::default(), Scope::Active);
+        let totals_of = |tag: &str| totals[tag];
+        assert_eq!(totals_of("work"), Totals { count: 3, seconds: 2400 });
+        assert_eq!(totals_of("work/clientA"), Totals { count: 2, seconds: 1800 });
+        assert_eq!(totals_of("work/clientA/backend"), Totals { count: 2, seconds: 1800 });
+        assert_eq!(totals_of("work/clientA/frontend"), Totals { count: 1, seconds: 1200 });
+
+        assert_eq!(
+            tree_order(&totals, None),
+            vec!["work", "work/clientA", "work/clientA/backend", "work/clientA/frontend", "work/clientB"]
+        );
+        assert_eq!(tree_order(&totals, Some(2)), vec!["work", "work/clientA", "work/clientB"]);
+
+        let filter = Filter {
+            tags: vec!["work/clientA".to_string()],
+            ..Filter::default()
+        };
+        let totals = get_tag_totals(&config, &filter, Scope::Active);
+        assert_eq!(totals["work"].count, 2);
+        assert!(!totals.contains_key("work/clientB"));
+
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+
+    #[test]
+    fn test_change_tags() {
+        let test_dir = std::env::temp_dir().join("acty_test_change_tags");
+        let _ = fs::remove_dir_all(&test_dir);
+        let config = Config {
+            log_file: test_dir.join("action_log.json").to_string_lossy().to_string(),
+            ..Config::default()
+        };
+        let old = Some(Local::now() - chrono::Duration::days(10));
+        let tags = |tags: &[&str]| tags.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+
+        log_action(&config, "Old".to_string(), tags(&["wrk"]), None, old, None);
+        crate::logger::archive_logs(&config, 7);
+        log_action(&config, "API".to_string(), tags(&["wrk/clientA", "urgent"]), None, None, None);
+        log_action(&config, "Call".to_string(), tags(&["work", "wrk"]), None, None, None);
+        log_action(&config, "Plan".to_string(), tags(&["workshop"]), None, None, None);
+        let read = |store| -> Vec<Vec<String>> {
+            storage::open(&config)
+                .read(store, &Filter::default())
+                .into_iter()
+                .map(|e| sort_tags(e.tags))
+                .collect()
+        };
+
+        rename_tag(&config, "wrk", "work", Scope::Active, true);
+        assert_eq!(read(Store::Active)[0], tags(&["urgent", "wrk/clientA"]));
+
+        rename_tag(&config, "wrk", "work", Scope::Active, false);
+        assert_eq!(
+            read(Store::Active),
+            vec![tags(&["urgent", "work/clientA"]), tags(&["work"]), tags(&["workshop"])]
+        );
+        assert_eq!(read(Store::Archive), vec![tags(&["wrk"])]);
+
+        rename_tag(&config, "wrk", "work", Scope::All, false);
+        assert_eq!(read(Store::Archive), vec![tags(&["work"])]);
 
-        log_action(&config, "Log 1".to_string(), vec!["work".to_string(), "urgent".to_string()]);
-        log_action(&config, "Log 2".to_string(), vec!["work".to_string(), "meeting".to_string()]);
-        log_action(&config, "Log 3".to_string(), vec!["rest".to_string()]);
+        merge_tags(&config, &tags(&["urgent", "workshop"]), "later", Scope::Active, false);
+        delete_tag(&config, "work", Scope::Active, false);
+        assert_eq!(read(Store::Active), vec![tags(&["later"]), tags(&[]), tags(&["later"])]);
 
-        let counts = get_tag_counts(&config, false);
-        
-        assert_eq!(*counts.get("work").unwrap(), 2);
-        assert_eq!(*counts.get("urgent").unwrap(), 1);
-        assert_eq!(*counts.get("meeting").unwrap(), 1);
-        assert_eq!(*counts.get("rest").unwrap(), 1);
-        assert!(counts.get("unknown").is_none());
+        crate::journal::undo(&config);
+        assert_eq!(read(Store::Active)[1], tags(&["work"]));
 
-        fs::remove_file(test_json_path).unwrap();
+        fs::remove_dir_all(test_dir).unwrap();
     }
 }
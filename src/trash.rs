@@ -0,0 +1,236 @@
+use crate::config::Config;
+use crate::journal::{self, Change};
+use crate::log_entry::LogEntry;
+use crate::logger::resolve_id;
+use crate::output::{self, EntryRecord, OutputFormat};
+use crate::storage::{self, Storage, Store};
+use crate::util::{sort_tags, Filter};
+use chrono::{DateTime, Local, TimeDelta};
+
+/// Moves entries to the trash, stamping them with the deletion time.
+/// Returns the trashed entries and the changes for the journal.
+pub fn move_to_trash(
+    storage: &dyn Storage,
+    from: Store,
+    ids: &[String],
+) -> (Vec<LogEntry>, Vec<Change>) {
+    let moved = storage.move_entries(from, Store::Trash, ids);
+    if moved.is_empty() {
+        return (moved, Vec::new());
+    }
+
+    let now = Local::now();
+    let mut changes = vec![Change::Move {
+        from,
+        to: Store::Trash,
+        entries: moved.clone(),
+    }];
+    let mut trashed = Vec::new();
+    for entry in moved {
+        let mut after = entry.clone();
+        after.deleted_at = Some(now);
+        storage.update(Store::Trash, &after);
+        trashed.push(after.clone());
+        changes.push(Change::Update {
+            store: Store::Trash,
+            before: Box::new(entry),
+            after: Box::new(after),
+        });
+    }
+    (trashed, changes)
+}
+
+pub fn list_trash(config: &Config, format: OutputFormat) {
+    let entries = {
+        let _lock = storage::lock_shared(config);
+        storage::open(config).read(Store::Trash, &Filter::default())
+    };
+
+    if format != OutputFormat::Table {
+        let records: Vec<EntryRecord> = entries
+            .iter()
+            .map(|e| EntryRecord::new(Store::Trash, e, e.duration))
+            .collect();
+        output::print_json(format, "trash", "entry", &records);
+        return;
+    }
+
+    if entries.is_empty() {
+        println!("The trash is empty.");
+        return;
+    }
+
+    println!("ID\tDeleted\t\t\tTime\t\t\tTags\t\tContent");
+    println!("--\t-------\t\t\t----\t\t\t----\t\t-------");
+    for entry in &entries {
+        println!(
+            "{}\t{}\t{}\t{}\t{}",
+            entry.id,
+            entry
+                .deleted_at
+                .map(|d| d.format(&config.time_format).to_string())
+                .unwrap_or_else(|| "-".to_string()),
+            entry.timestamp.format(&config.time_format),
+            sort_tags(entry.tags.clone()).join(", "),
+            entry.content,
+        );
+    }
+}
+
+/// Moves entries from the trash back to their chronological position in the
+/// log.
+pub fn restore(config: &Config, selectors: Vec<String>) {
+    let _lock = storage::lock(config);
+    let storage = storage::open(config);
+    let entries = storage.read(Store::Trash, &Filter::default());
+    let entry_ids: Vec<String> = entries.iter().map(|e| e.id.clone()).collect();
+
+    let mut selected: Vec<LogEntry> = Vec::new();
+    for selector in &selectors {
+        match resolve_id(&entry_ids, selector) {
+            Ok(index) => {
+                if !selected.iter().any(|e| e.id == entry_ids[index]) {
+                    selected.push(entries[index].clone());
+                }
+            }
+            Err(e) => eprintln!("Skipping {}", e),
+        }
+    }
+
+    if selected.is_empty() {
+        println!("0 log entry(ies) restored.");
+        return;
+    }
+
+    let mut changes = Vec::new();
+    for entry in &selected {
+        let mut after = entry.clone();
+        after.deleted_at = None;
+        storage.update(Store::Trash, &after);
+        changes.push(Change::Update {
+            store: Store::Trash,
+            before: Box::new(entry.clone()),
+            after: Box::new(after),
+        });
+    }
+    let ids: Vec<String> = selected.iter().map(|e| e.id.clone()).collect();
+    let restored = storage.move_entries(Store::Trash, Store::Active, &ids);
+    changes.push(Change::Move {
+        from: Store::Trash,
+        to: Store::Active,
+        entries: restored.clone(),
+    });
+    journal::record(
+        config,
+        &format!("trash restore {}", selectors.join(" ")),
+        changes,
+    );
+
+    println!("{} log entry(ies) restored.", restored.len());
+}
+
+/// Permanently deletes trashed entries, or only those deleted more than
+/// `older_than` seconds ago. They are removed from the journal too, so this
+/// can't be undone.
+pub fn empty_trash(config: &Config, older_than: Option<i64>) {
+    let cutoff = match older_than {
+        Some(seconds) => match TimeDelta::try_seconds(seconds)
+            .and_then(|age| Local::now().checked_sub_signed(age))
+        {
+            Some(cutoff) => Some(cutoff),
+            None => {
+                eprintln!("Invalid --older-than: {} seconds is too long.", seconds);
+                return;
+            }
+        },
+        None => None,
+    };
+    let _lock = storage::lock(config);
+    let storage = storage::open(config);
+    let expired: Vec<String> = storage
+        .read(Store::Trash, &Filter::default())
+        .into_iter()
+        .filter(|entry| cutoff.is_none_or(|cutoff| deleted_at(entry) <= cutoff))
+        .map(|entry| entry.id)
+        .collect();
+
+    let removed = if expired.is_empty() {
+        Vec::new()
+    } else {
+        storage.delete(Store::Trash, &expired)
+    };
+    if !removed.is_empty() {
+        let ids: Vec<String> = removed.iter().map(|e| e.id.clone()).collect();
+        journal::forget(config, &ids);
+    }
+
+    println!("{} log entry(ies) permanently deleted.", removed.len());
+}
+
+// Entries trashed by an interrupted delete may lack the stamp.
+fn deleted_at(entry: &LogEntry) -> DateTime<Local> {
+    entry.deleted_at.unwrap_or(entry.timestamp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logger::{delete_logs, log_action};
+    use std::fs;
+
+    #[test]
+    fn test_trash_restore_and_empty() {
+        let test_dir = std::env::temp_dir().join("acty_test_trash");
+        let _ = fs::remove_dir_all(&test_dir);
+        let config = Config {
+            log_file: test_dir.join("action_log.json").to_string_lossy().to_string(),
+            ..Config::default()
+        };
+        let contents = |store: Store| -> Vec<String> {
+            storage::open(&config)
+                .read(store, &Filter::default())
+                .into_iter()
+                .map(|e| e.content)
+                .collect()
+        };
+
+        log_action(&config, "First".to_string(), vec![], None, None, None);
+        log_action(&config, "Second".to_string(), vec![], None, None, None);
+        log_action(&config, "Third".to_string(), vec![], None, None, None);
+        delete_logs(&config, vec!["1".to_string(), "2".to_string()]);
+
+        assert_eq!(contents(Store::Active), vec!["Third"]);
+        let trashed = storage::open(&config).read(Store::Trash, &Filter::default());
+        assert_eq!(trashed.len(), 2);
+        assert!(trashed.iter().all(|e| e.deleted_at.is_some()));
+
+        // Restored entries go back to their original position
+        restore(&config, vec![trashed[1].id.clone()]);
+        assert_eq!(contents(Store::Active), vec!["Second", "Third"]);
+        assert!(storage::open(&config).read(Store::Active, &Filter::default())[0]
+            .deleted_at
+            .is_none());
+
+        empty_trash(&config, Some(3600));
+        assert_eq!(contents(Store::Trash), vec!["First"]);
+        empty_trash(&config, None);
+        assert!(contents(Store::Trash).is_empty());
+
+        // Emptied entries are gone for good, from the journal too
+        let history = fs::read_to_string(test_dir.join("action_log.json.history")).unwrap();
+        assert!(!history.contains("First"));
+        assert!(history.contains("Second"));
+        // Undo reaches past it to the restore, and the delete before that
+        // only brings back the entry that is still around
+        crate::journal::undo(&config);
+        assert_eq!(contents(Store::Trash), vec!["Second"]);
+        crate::journal::undo(&config);
+        assert_eq!(contents(Store::Active), vec!["Second", "Third"]);
+        assert!(contents(Store::Trash).is_empty());
+
+        // An age too large to subtract from now is reported, not a panic
+        empty_trash(&config, Some(i64::MAX));
+
+        fs::remove_dir_all(test_dir).unwrap();
+    }
+}
@@ -0,0 +1,570 @@
+use crate::config::Config;
+use crate::log_entry::LogEntry;
+use crate::logger;
+use crate::query::Query;
+use crate::storage::{self, Scope, Store};
+use crate::util::{format_duration, sort_tags, Filter};
+use chrono::Local;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Paragraph, Row, Table, TableState, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use std::io::{self, IsTerminal};
+
+/// How far PageUp and PageDown move the selection.
+const PAGE: isize = 10;
+
+const BROWSE_HELP: &str =
+    "↑↓/jk move  / filter  e edit  t retag  c copy  d delete  r reload  q quit";
+const FILTER_HELP: &str = "Type a query (words, tag:NAME, and/or/not)  Enter keep  Esc clear";
+
+/// What the keys currently do.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mode {
+    Browse,
+    /// Typing the filter; the list follows every key.
+    Filter,
+    EditContent,
+    Retag,
+    ConfirmDelete,
+}
+
+/// The state of `acty tui`: the entries read with the command line filter,
+/// the ones the typed filter lets through and the selection among them.
+struct Browser {
+    /// For the tag aliases in the typed filter.
+    config: Config,
+    entries: Vec<(Store, LogEntry)>,
+    durations: Vec<Option<i64>>,
+    /// Positions in `entries` that match the typed filter.
+    visible: Vec<usize>,
+    table: TableState,
+    filter: String,
+    filter_error: Option<String>,
+    /// The entry to select again once a filter that hides everything is
+    /// changed.
+    hidden_selection: Option<String>,
+    mode: Mode,
+    input: String,
+    message: String,
+}
+
+impl Browser {
+    fn new(config: &Config, entries: Vec<(Store, LogEntry, Option<i64>)>) -> Browser {
+        let mut browser = Browser {
+            config: config.clone(),
+            entries: Vec::new(),
+            durations: Vec::new(),
+            visible: Vec::new(),
+            table: TableState::default(),
+            filter: String::new(),
+            filter_error: None,
+            hidden_selection: None,
+            mode: Mode::Browse,
+            input: String::new(),
+            message: String::new(),
+        };
+        browser.load(entries);
+        // Start at the latest entry, like the end of `list`
+        browser.move_by(isize::MAX);
+        browser
+    }
+
+    /// Replaces the entries, keeping the selection on the same entry when it
+    /// is still there.
+    fn load(&mut self, entries: Vec<(Store, LogEntry, Option<i64>)>) {
+        let selected = self.selected().map(|(_, e)| e.id.clone());
+        self.durations = entries.iter().map(|(_, _, duration)| *duration).collect();
+        self.entries = entries.into_iter().map(|(store, entry, _)| (store, entry)).collect();
+        self.visible = (0..self.entries.len()).collect();
+        if let Some(id) = selected {
+            self.select_id(&id);
+        }
+        self.refilter();
+    }
+
+    fn set_filter(&mut self, filter: String) {
+        self.filter = filter;
+        self.refilter();
+    }
+
+    /// Applies the typed filter. While it doesn't parse (say, halfway
+    /// through `tag:`), the list stays as it was.
+    fn refilter(&mut self) {
+        let selected = self
+            .selected()
+            .map(|(_, e)| e.id.clone())
+            .or_else(|| self.hidden_selection.clone());
+        let query = if self.filter.trim().is_empty() {
+            None
+        } else {
+            match Query::parse(&self.filter, Local::now(), &self.config) {
+                Ok(query) => Some(query),
+                Err(e) => {
+                    self.filter_error = Some(e);
+                    return;
+                }
+            }
+        };
+        self.filter_error = None;
+        self.visible = (0..self.entries.len())
+            .filter(|&i| query.as_ref().is_none_or(|q| q.matches(&self.entries[i].1)))
+            .collect();
+        self.hidden_selection = if self.visible.is_empty() {
+            selected.clone()
+        } else {
+            None
+        };
+
+        let position = selected
+            .and_then(|id| self.visible.iter().position(|&i| self.entries[i].1.id == id))
+            .or(self.table.selected());
+        self.table.select(position);
+        self.move_by(0);
+    }
+
+    fn selected(&self) -> Option<&(Store, LogEntry)> {
+        let position = self.table.selected()?;
+        self.visible.get(position).map(|&i| &self.entries[i])
+    }
+
+    /// Selects the entry with `id`, if it is shown.
+    fn select_id(&mut self, id: &str) {
+        if let Some(position) = self.visible.iter().position(|&i| self.entries[i].1.id == id) {
+            self.table.select(Some(position));
+        }
+    }
+
+    /// Moves the selection, stopping at either end of the list.
+    fn move_by(&mut self, delta: isize) {
+        if self.visible.is_empty() {
+            self.table.select(None);
+            return;
+        }
+        let current = self.table.selected().unwrap_or(0) as isize;
+        let last = self.visible.len() as isize - 1;
+        self.table
+            .select(Some(current.saturating_add(delta).clamp(0, last) as usize));
+    }
+}
+
+/// A full-screen browser over the entries `list` would show with the same
+/// options, with a filter typed on the fly and edits made in place.
+pub fn browse(config: &Config, filter: Filter, scope: Scope) {
+    if !io::stdout().is_terminal() {
+        eprintln!("acty tui needs a terminal; use `acty list` in scripts.");
+        return;
+    }
+    let read = || {
+        let _lock = storage::lock_shared(config);
+        storage::read_scope_timed(storage::open(config).as_ref(), scope, &filter, Local::now())
+    };
+
+    let mut browser = Browser::new(config, read());
+    let mut terminal = ratatui::try_init().expect("Unable to set up the terminal");
+    let result = run(&mut terminal, config, &mut browser, &read);
+    ratatui::restore();
+    result.expect("Unable to draw to the terminal");
+}
+
+fn run(
+    terminal: &mut DefaultTerminal,
+    config: &Config,
+    browser: &mut Browser,
+    read: &dyn Fn() -> Vec<(Store, LogEntry, Option<i64>)>,
+) -> io::Result<()> {
+    loop {
+        terminal.draw(|frame| draw(frame, config, browser))?;
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press && !handle_key(config, browser, key, read) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Reacts to a key press; returns false to quit.
+fn handle_key(
+    config: &Config,
+    browser: &mut Browser,
+    key: KeyEvent,
+    read: &dyn Fn() -> Vec<(Store, LogEntry, Option<i64>)>,
+) -> bool {
+    if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+        return false;
+    }
+
+    match browser.mode {
+        Mode::Browse => {
+            browser.message.clear();
+            match key.code {
+                KeyCode::Char('q') => return false,
+                KeyCode::Esc if browser.filter.is_empty() => return false,
+                KeyCode::Esc => browser.set_filter(String::new()),
+                KeyCode::Down | KeyCode::Char('j') => browser.move_by(1),
+                KeyCode::Up | KeyCode::Char('k') => browser.move_by(-1),
+                KeyCode::PageDown => browser.move_by(PAGE),
+                KeyCode::PageUp => browser.move_by(-PAGE),
+                KeyCode::Home | KeyCode::Char('g') => browser.move_by(isize::MIN),
+                KeyCode::End | KeyCode::Char('G') => browser.move_by(isize::MAX),
+                KeyCode::Char('/') => browser.mode = Mode::Filter,
+                KeyCode::Char('r') => {
+                    browser.load(read());
+                    browser.message = "Reloaded.".to_string();
+                }
+                KeyCode::Char('e') => {
+                    if let Some((_, entry)) = browser.selected() {
+                        browser.input = entry.content.clone();
+                        browser.mode = Mode::EditContent;
+                    }
+                }
+                KeyCode::Char('t') => {
+                    if let Some((_, entry)) = browser.selected() {
+                        browser.input = sort_tags(entry.tags.clone()).join(", ");
+                        browser.mode = Mode::Retag;
+                    }
+                }
+                KeyCode::Char('d') if browser.selected().is_some() => {
+                    browser.mode = Mode::ConfirmDelete;
+                }
+                KeyCode::Char('c') => copy(config, browser, read),
+                _ => {}
+            }
+        }
+        Mode::Filter => match key.code {
+            KeyCode::Enter => browser.mode = Mode::Browse,
+            KeyCode::Esc => {
+                browser.set_filter(String::new());
+                browser.mode = Mode::Browse;
+            }
+            KeyCode::Backspace => {
+                let mut filter = browser.filter.clone();
+                filter.pop();
+                browser.set_filter(filter);
+            }
+            KeyCode::Char(c) => {
+                let filter = format!("{}{}", browser.filter, c);
+                browser.set_filter(filter);
+            }
+            _ => {}
+        },
+        Mode::EditContent | Mode::Retag => match key.code {
+            KeyCode::Enter => {
+                save(config, browser, read);
+                browser.mode = Mode::Browse;
+            }
+            KeyCode::Esc => browser.mode = Mode::Browse,
+            KeyCode::Backspace => {
+                browser.input.pop();
+            }
+            KeyCode::Char(c) => browser.input.push(c),
+            _ => {}
+        },
+        Mode::ConfirmDelete => {
+            if key.code == KeyCode::Char('y') {
+                delete(config, browser, read);
+            }
+            browser.mode = Mode::Browse;
+        }
+    }
+    true
+}
+
+/// The selected entry's ID in the form the `logger` operations take.
+fn selector(browser: &Browser) -> Option<String> {
+    browser
+        .selected()
+        .map(|(store, entry)| store.display_id(&entry.id))
+}
+
+/// Stores the content or tags typed for the selected entry.
+fn save(config: &Config, browser: &mut Browser, read: &dyn Fn() -> Vec<(Store, LogEntry, Option<i64>)>) {
+    let id = match selector(browser) {
+        Some(id) => id,
+        None => return,
+    };
+    let input = browser.input.trim().to_string();
+    let (content, tags) = if browser.mode == Mode::EditContent {
+        if input.is_empty() {
+            browser.message = "The content can't be empty.".to_string();
+            return;
+        }
+        (Some(input), None)
+    } else {
+        let tags = input
+            .split(',')
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect();
+        (None, Some(tags))
+    };
+
+    browser.message = match logger::edit_entry(config, &id, content, tags, None, None) {
+        Ok(_) => {
+            browser.load(read());
+            format!("Log entry {} updated.", id)
+        }
+        Err(e) => e,
+    };
+}
+
+fn copy(config: &Config, browser: &mut Browser, read: &dyn Fn() -> Vec<(Store, LogEntry, Option<i64>)>) {
+    let id = match selector(browser) {
+        Some(id) => id,
+        None => return,
+    };
+    browser.message = match logger::copy_entry(config, &id, None, None) {
+        Ok((_, _, entry)) => {
+            browser.load(read());
+            browser.select_id(&entry.id);
+            format!("Log entry {} copied to new entry {}.", id, entry.id)
+        }
+        Err(e) => e,
+    };
+}
+
+fn delete(config: &Config, browser: &mut Browser, read: &dyn Fn() -> Vec<(Store, LogEntry, Option<i64>)>) {
+    let id = match selector(browser) {
+        Some(id) => id,
+        None => return,
+    };
+    logger::trash_entries(config, std::slice::from_ref(&id));
+    browser.load(read());
+    browser.message = format!("Log entry {} moved to the trash (acty undo brings it back).", id);
+}
+
+fn draw(frame: &mut Frame, config: &Config, browser: &mut Browser) {
+    let [filter_area, main_area, status_area] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Min(0),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+    let [list_area, detail_area] =
+        Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)])
+            .areas(main_area);
+    let (id_style, tag_style) = if config.use_color() {
+        (Style::new().fg(Color::Yellow), Style::new().fg(Color::Cyan))
+    } else {
+        (Style::new(), Style::new())
+    };
+
+    let filter_line = if browser.filter.is_empty() && browser.mode != Mode::Filter {
+        Line::from("Filter: none (press / to type one)")
+    } else {
+        Line::from(format!("Filter: {}", browser.filter))
+    };
+    if browser.mode == Mode::Filter {
+        set_cursor(frame, filter_area, &filter_line);
+    }
+    frame.render_widget(Paragraph::new(filter_line), filter_area);
+
+    draw_list(frame, config, browser, list_area, id_style, tag_style);
+    draw_detail(frame, config, browser, detail_area, id_style, tag_style);
+
+    let status = match browser.mode {
+        Mode::Browse if browser.message.is_empty() => Line::from(BROWSE_HELP),
+        Mode::Browse => Line::from(browser.message.as_str()),
+        Mode::Filter => match &browser.filter_error {
+            Some(e) => Line::from(Span::styled(e.as_str(), Style::new().fg(Color::Red))),
+            None => Line::from(FILTER_HELP),
+        },
+        Mode::EditContent => Line::from(format!("Content: {}", browser.input)),
+        Mode::Retag => Line::from(format!("Tags (comma-separated): {}", browser.input)),
+        Mode::ConfirmDelete => Line::from(format!(
+            "Move {} to the trash? (y/n)",
+            selector(browser).unwrap_or_default()
+        )),
+    };
+    if matches!(browser.mode, Mode::EditContent | Mode::Retag) {
+        set_cursor(frame, status_area, &status);
+    }
+    frame.render_widget(Paragraph::new(status), status_area);
+}
+
+/// Puts the cursor after the text on a one-line input.
+fn set_cursor(frame: &mut Frame, area: Rect, line: &Line) {
+    let x = area.x.saturating_add(line.width() as u16);
+    frame.set_cursor_position((x.min(area.right().saturating_sub(1)), area.y));
+}
+
+fn draw_list(
+    frame: &mut Frame,
+    config: &Config,
+    browser: &mut Browser,
+    area: Rect,
+    id_style: Style,
+    tag_style: Style,
+) {
+    let cells: Vec<[String; 5]> = browser
+        .visible
+        .iter()
+        .map(|&i| {
+            let (store, entry) = &browser.entries[i];
+            [
+                store.display_id(&entry.id),
+                entry.timestamp.format(&config.time_format).to_string(),
+                duration_text(entry, browser.durations[i]),
+                sort_tags(entry.tags.clone()).join(", "),
+                entry.summary(),
+            ]
+        })
+        .collect();
+    let width = |column: usize, header: &str, most: usize| {
+        let widest = cells
+            .iter()
+            .map(|row| row[column].chars().count())
+            .max()
+            .unwrap_or(0);
+        Constraint::Length(widest.max(header.len()).min(most) as u16)
+    };
+    let widths = [
+        width(0, "ID", 12),
+        width(1, "Time", 24),
+        width(2, "Duration", 20),
+        width(3, "Tags", 24),
+        Constraint::Min(10),
+    ];
+
+    let rows: Vec<Row> = cells
+        .into_iter()
+        .map(|[id, time, duration, tags, content]| {
+            Row::new(vec![
+                Span::styled(id, id_style),
+                Span::raw(time),
+                Span::raw(duration),
+                Span::styled(tags, tag_style),
+                Span::raw(content),
+            ])
+        })
+        .collect();
+    let title = format!(
+        " {} ({} of {}) ",
+        config.book,
+        browser.visible.len(),
+        browser.entries.len()
+    );
+    let table = Table::new(rows, widths)
+        .header(
+            Row::new(vec!["ID", "Time", "Duration", "Tags", "Content"])
+                .style(Style::new().add_modifier(Modifier::BOLD)),
+        )
+        .block(Block::bordered().title(title))
+        .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+    frame.render_stateful_widget(table, area, &mut browser.table);
+}
+
+fn draw_detail(
+    frame: &mut Frame,
+    config: &Config,
+    browser: &Browser,
+    area: Rect,
+    id_style: Style,
+    tag_style: Style,
+) {
+    let block = Block::bordered().title(" Details ");
+    let position = browser
+        .table
+        .selected()
+        .and_then(|p| browser.visible.get(p).copied());
+    let position = match position {
+        Some(position) => position,
+        None => {
+            frame.render_widget(Paragraph::new("No logs found.").block(block), area);
+            return;
+        }
+    };
+    let (store, entry) = &browser.entries[position];
+
+    let field = |name: &str, value: Span<'static>| {
+        Line::from(vec![
+            Span::styled(format!("{:<9}", name), Style::new().add_modifier(Modifier::BOLD)),
+            value,
+        ])
+    };
+    let mut lines = vec![
+        field("ID", Span::styled(store.display_id(&entry.id), id_style)),
+        field("Stored", Span::raw(store.name())),
+        field(
+            "Time",
+            Span::raw(entry.timestamp.format(&config.time_format).to_string()),
+        ),
+        field(
+            "Duration",
+            Span::raw(duration_text(entry, browser.durations[position])),
+        ),
+        field(
+            "Tags",
+            Span::styled(sort_tags(entry.tags.clone()).join(", "), tag_style),
+        ),
+        Line::default(),
+    ];
+    lines.extend(entry.content.lines().map(|line| Line::from(line.to_string())));
+    if let Some(notes) = &entry.notes {
+        lines.push(Line::default());
+        lines.extend(notes.lines().map(|line| Line::from(line.to_string())));
+    }
+
+    frame.render_widget(
+        Paragraph::new(lines).block(block).wrap(Wrap { trim: false }),
+        area,
+    );
+}
+
+fn duration_text(entry: &LogEntry, duration: Option<i64>) -> String {
+    match duration {
+        Some(seconds) if entry.running => format!("{} (running)", format_duration(seconds)),
+        Some(seconds) => format_duration(seconds),
+        None => "-".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, Duration};
+
+    #[test]
+    fn test_browser_filter() {
+        let start = DateTime::parse_from_rfc3339("2026-10-01T09:00:00+00:00")
+            .unwrap()
+            .with_timezone(&Local);
+        let entry = |minutes: i64, content: &str, tags: &[&str]| {
+            let tags = tags.iter().map(|t| t.to_string()).collect();
+            (
+                Store::Active,
+                LogEntry::new(start + Duration::minutes(minutes), content.to_string(), tags),
+                None,
+            )
+        };
+        let mut browser = Browser::new(&Config::default(), vec![
+            entry(0, "Standup", &["work"]),
+            entry(30, "Coffee", &["break"]),
+            entry(45, "Deploy", &["work", "ops"]),
+        ]);
+        assert_eq!(browser.selected().unwrap().1.content, "Deploy");
+
+        browser.set_filter("tag:work".to_string());
+        assert_eq!(browser.visible, vec![0, 2]);
+        // The selection follows the entry, not the row
+        assert_eq!(browser.selected().unwrap().1.content, "Deploy");
+        browser.move_by(-5);
+        assert_eq!(browser.selected().unwrap().1.content, "Standup");
+
+        // A filter that doesn't parse yet leaves the list alone
+        browser.set_filter("tag:work and".to_string());
+        assert!(browser.filter_error.is_some());
+        assert_eq!(browser.visible, vec![0, 2]);
+
+        browser.set_filter("coff".to_string());
+        assert_eq!(browser.visible, vec![1]);
+        assert_eq!(browser.selected().unwrap().1.content, "Coffee");
+
+        browser.set_filter("nothing".to_string());
+        assert!(browser.selected().is_none());
+        browser.set_filter(String::new());
+        assert_eq!(browser.selected().unwrap().1.content, "Coffee");
+    }
+}
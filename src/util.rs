@@ -1,5 +1,7 @@
+use crate::dates::DateRange;
 use crate::log_entry::LogEntry;
-use chrono::{Local, NaiveDate};
+use crate::query::Query;
+use chrono::{DateTime, Local, NaiveDate, TimeDelta};
 
 pub fn sort_tags(mut tags: Vec<String>) -> Vec<String> {
     tags.sort_by(|a, b| {
@@ -13,16 +15,176 @@ pub fn sort_tags(mut tags: Vec<String>) -> Vec<String> {
     tags
 }
 
+/// Separates the levels of a hierarchical tag such as `work/clientA/backend`.
+pub const TAG_SEPARATOR: char = '/';
+
+/// Whether `tag` is `filter` or lies below it: `work` matches `work` and
+/// `work/clientA`, but not `workshop`.
+pub fn tag_matches(tag: &str, filter: &str) -> bool {
+    let filter = filter.trim_end_matches(TAG_SEPARATOR);
+    match tag.strip_prefix(filter) {
+        Some(rest) => rest.is_empty() || rest.starts_with(TAG_SEPARATOR),
+        None => false,
+    }
+}
+
+/// `tag` cut down to its first `depth` levels.
+pub fn tag_at_depth(tag: &str, depth: usize) -> &str {
+    match tag.match_indices(TAG_SEPARATOR).nth(depth.saturating_sub(1)) {
+        Some((index, _)) => &tag[..index],
+        None => tag,
+    }
+}
+
+/// `tag` and every level above it, from the top: `work`, `work/clientA`,
+/// `work/clientA/backend`.
+pub fn tag_ancestors(tag: &str) -> impl Iterator<Item = &str> {
+    tag.match_indices(TAG_SEPARATOR)
+        .map(move |(index, _)| &tag[..index])
+        .chain(std::iter::once(tag))
+}
+
+pub fn format_duration(seconds: i64) -> String {
+    if seconds < 60 {
+        format!("{}s", seconds)
+    } else if seconds < 3600 {
+        format!("{}m", seconds / 60)
+    } else {
+        let hours = seconds / 3600;
+        let minutes = (seconds % 3600) / 60;
+        format!("{}h {}m", hours, minutes)
+    }
+}
+
+/// The longest duration an entry can have, in seconds: just under 100 years.
+pub const MAX_DURATION: i64 = 100 * 365 * 86400;
+
+/// Parses durations such as `25m`, `1h30m`, `90s`, `1.5h` or `30d`. A bare
+/// number is taken as minutes. Anything over [`MAX_DURATION`] is rejected.
+pub fn parse_duration(input: &str) -> Result<i64, String> {
+    let invalid = || format!("Invalid duration: '{}'. Use e.g. 25m, 1h30m or 90s.", input);
+    let trimmed = input.trim();
+    if trimmed.is_empty() {
+        return Err(invalid());
+    }
+    let seconds = |total: f64| {
+        if total.is_finite() && total >= 0.0 && total.round() <= MAX_DURATION as f64 {
+            Ok(total.round() as i64)
+        } else {
+            Err(invalid())
+        }
+    };
+    if let Ok(minutes) = trimmed.parse::<f64>() {
+        return seconds(minutes * 60.0);
+    }
+
+    let mut total = 0.0;
+    let mut number = String::new();
+    for c in trimmed.chars() {
+        if c.is_ascii_digit() || c == '.' {
+            number.push(c);
+            continue;
+        }
+        let unit = match c {
+            'w' => 7.0 * 86400.0,
+            'd' => 86400.0,
+            'h' => 3600.0,
+            'm' => 60.0,
+            's' => 1.0,
+            _ => return Err(invalid()),
+        };
+        let value: f64 = number.parse().map_err(|_| invalid())?;
+        total += value * unit;
+        number.clear();
+    }
+    if !number.is_empty() {
+        return Err(invalid());
+    }
+    seconds(total)
+}
+
+/// The time attributed to each entry: its own duration when it has one (the
+/// elapsed time so far for a running timer), otherwise the gap since the end
+/// of the previous entry. The first entry without a duration has none.
+/// Stored durations are capped at [`MAX_DURATION`], so totals never overflow.
+pub fn entry_durations<'a>(
+    entries: impl IntoIterator<Item = &'a LogEntry>,
+    now: DateTime<Local>,
+) -> Vec<Option<i64>> {
+    let mut previous_end: Option<DateTime<Local>> = None;
+    entries
+        .into_iter()
+        .map(|entry| {
+            let duration = if entry.running {
+                Some((now - entry.timestamp).num_seconds().max(0))
+            } else if let Some(d) = entry.duration {
+                Some(d.clamp(0, MAX_DURATION))
+            } else {
+                previous_end.map(|end| (entry.timestamp - end).num_seconds().max(0))
+            };
+            // A duration too long to add (say, from a hand-edited log) ends
+            // the entry where it starts rather than failing
+            previous_end = Some(match entry.duration {
+                Some(d) if !entry.running => TimeDelta::try_seconds(d.clamp(0, MAX_DURATION))
+                    .and_then(|d| entry.timestamp.checked_add_signed(d))
+                    .unwrap_or(entry.timestamp),
+                _ => entry.timestamp,
+            });
+            duration
+        })
+        .collect()
+}
+
+/// The filter options shared by every command that reads the log.
+#[derive(Default, Debug, Clone)]
+pub struct Filter {
+    pub dates: Option<DateRange>,
+    pub range: Option<i64>,
+    pub tags: Vec<String>,
+    pub search: Option<String>,
+    pub query: Option<Query>,
+}
+
+impl Filter {
+    pub fn matches(&self, log_entry: &LogEntry) -> bool {
+        should_include_log(log_entry, &self.dates, &self.range, &self.tags, &self.search)
+            && self.query.as_ref().is_none_or(|q| q.matches(log_entry))
+    }
+
+    /// The first and last day a matching entry can be on, so stores that
+    /// index entries by time can look up only those days.
+    pub fn date_bounds(&self) -> (Option<NaiveDate>, Option<NaiveDate>) {
+        let mut from = self.dates.as_ref().and_then(|d| d.from);
+        let to = self.dates.as_ref().and_then(|d| d.to);
+        if let Some(r) = self.range {
+            let earliest = Local::now().date_naive() - chrono::Duration::days(r);
+            from = Some(from.map_or(earliest, |from| from.max(earliest)));
+        }
+        (from, to)
+    }
+
+    /// Whether entries dated between `first` and `last` (inclusive) could
+    /// match, so readers can skip whole files outside the requested dates.
+    pub fn overlaps(&self, first: NaiveDate, last: NaiveDate) -> bool {
+        let in_dates = self.dates.is_none_or(|d| {
+            d.from.is_none_or(|from| last >= from) && d.to.is_none_or(|to| first <= to)
+        });
+        let in_range = self.range.is_none_or(|r| {
+            (Local::now().date_naive() - last).num_days() <= r
+        });
+        in_dates && in_range
+    }
+}
+
 pub fn should_include_log(
     log_entry: &LogEntry,
-    date: &Option<String>,
+    dates: &Option<DateRange>,
     range: &Option<i64>,
     tags: &[String],
     search: &Option<String>,
 ) -> bool {
-    if let Some(d) = date {
-        let filter_date = NaiveDate::parse_from_str(d, "%Y-%m-%d").expect("Invalid date format");
-        if log_entry.timestamp.date_naive() != filter_date {
+    if let Some(d) = dates {
+        if !d.contains(log_entry.timestamp.date_naive()) {
             return false;
         }
     }
@@ -35,7 +197,11 @@ pub fn should_include_log(
         }
     }
 
-    if !tags.is_empty() && !tags.iter().all(|t| log_entry.tags.contains(t)) {
+    if !tags.is_empty()
+        && !tags
+            .iter()
+            .all(|t| log_entry.tags.iter().any(|tag| tag_matches(tag, t)))
+    {
         return false;
     }
 
@@ -43,8 +209,12 @@ pub fn should_include_log(
         let s_lower = s.to_lowercase();
         let content_match = log_entry.content.to_lowercase().contains(&s_lower);
         let tag_match = log_entry.tags.iter().any(|t| t.to_lowercase().contains(&s_lower));
-        
-        if !content_match && !tag_match {
+        let notes_match = log_entry
+            .notes
+            .as_ref()
+            .is_some_and(|n| n.to_lowercase().contains(&s_lower));
+
+        if !content_match && !tag_match && !notes_match {
             return false;
         }
     }
@@ -60,15 +230,15 @@ mod tests {
 
     #[test]
     fn test_should_include_log_search() {
-        let entry = LogEntry {
-            timestamp: Local::now(),
-            content: "Meeting with the team".to_string(),
-            tags: vec!["work".to_string(), "urgent".to_string()],
-        };
+        let mut entry = LogEntry::new(
+            Local::now(),
+            "Meeting with the team".to_string(),
+            vec!["work".to_string(), "urgent".to_string()],
+        );
 
         // Match content (case insensitive)
         assert!(should_include_log(&entry, &None, &None, &[], &Some("meeting".to_string())));
-        
+
         // Match tag (should pass after update)
         assert!(should_include_log(&entry, &None, &None, &[], &Some("urgent".to_string())));
 
@@ -77,5 +247,84 @@ mod tests {
 
         // Match partial content
         assert!(should_include_log(&entry, &None, &None, &[], &Some("team".to_string())));
+
+        // Match the notes
+        entry.notes = Some("Agreed on the\nrelease date".to_string());
+        assert!(should_include_log(&entry, &None, &None, &[], &Some("Release".to_string())));
+    }
+
+    #[test]
+    fn test_hierarchical_tags() {
+        assert!(tag_matches("work/clientA/backend", "work"));
+        assert!(tag_matches("work/clientA", "work/clientA/"));
+        assert!(tag_matches("work", "work"));
+        assert!(!tag_matches("workshop", "work"));
+        assert!(!tag_matches("work", "work/clientA"));
+
+        assert_eq!(tag_at_depth("work/clientA/backend", 1), "work");
+        assert_eq!(tag_at_depth("work/clientA/backend", 2), "work/clientA");
+        assert_eq!(tag_at_depth("work", 2), "work");
+        assert_eq!(
+            tag_ancestors("work/clientA/backend").collect::<Vec<_>>(),
+            vec!["work", "work/clientA", "work/clientA/backend"]
+        );
+
+        let entry = LogEntry::new(Local::now(), "API".to_string(), vec!["work/clientA/backend".to_string()]);
+        assert!(should_include_log(&entry, &None, &None, &["work".to_string()], &None));
+        assert!(!should_include_log(&entry, &None, &None, &["work/clientB".to_string()], &None));
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("25m"), Ok(1500));
+        assert_eq!(parse_duration("1h30m"), Ok(5400));
+        assert_eq!(parse_duration("90s"), Ok(90));
+        assert_eq!(parse_duration("1.5h"), Ok(5400));
+        assert_eq!(parse_duration("10"), Ok(600));
+        assert_eq!(parse_duration("30d"), Ok(30 * 86400));
+        assert_eq!(parse_duration("1w1d"), Ok(8 * 86400));
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("5x").is_err());
+        assert!(parse_duration("h").is_err());
+        assert!(parse_duration("1h30").is_err());
+        for huge in ["1e300", "inf", "NaN", "-5", "36501d", "999999999999999999999h"] {
+            assert!(parse_duration(huge).unwrap_err().starts_with("Invalid duration"), "{}", huge);
+        }
+        assert_eq!(parse_duration("5200w"), Ok(5200 * 7 * 86400));
+    }
+
+    #[test]
+    fn test_entry_durations() {
+        let start = Local::now() - chrono::Duration::hours(3);
+        let first = LogEntry::new(start, "First".to_string(), vec![]);
+        let mut timed = LogEntry::new(
+            start + chrono::Duration::minutes(10),
+            "Timed".to_string(),
+            vec![],
+        );
+        timed.duration = Some(1800);
+        let after = LogEntry::new(
+            start + chrono::Duration::minutes(60),
+            "After".to_string(),
+            vec![],
+        );
+        let mut running = LogEntry::new(
+            start + chrono::Duration::minutes(90),
+            "Running".to_string(),
+            vec![],
+        );
+        running.running = true;
+
+        let now = start + chrono::Duration::minutes(100);
+        let durations = entry_durations(&[first, timed, after, running], now);
+        // The gap after a timed entry is measured from its end
+        assert_eq!(durations, vec![None, Some(1800), Some(1200), Some(600)]);
+
+        // A broken duration, e.g. written by an older acty, doesn't panic
+        let mut broken = LogEntry::new(start, "Broken".to_string(), vec![]);
+        broken.duration = Some(i64::MAX);
+        let later = LogEntry::new(start + chrono::Duration::minutes(10), "Later".to_string(), vec![]);
+        let durations = entry_durations(&[broken.clone(), broken, later], now);
+        assert_eq!(durations, vec![Some(MAX_DURATION), Some(MAX_DURATION), Some(0)]);
     }
 }